@@ -0,0 +1,377 @@
+use crate::tides::{level_at, Tide, TidePoint};
+use chrono::{DateTime, Duration, NaiveTime, Timelike, Utc};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// How often the interpolated curve is sampled when evaluating `level` rules.
+const SAMPLE_MINUTES: i64 = 6;
+
+/// What part of the tide a rule looks at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subject {
+    /// The continuous, interpolated sea level.
+    Level,
+    /// Predicted high tides only.
+    High,
+    /// Predicted low tides only.
+    Low,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Below,
+    AtMost,
+    Above,
+    AtLeast,
+}
+
+impl Comparison {
+    fn holds(&self, level: f32, threshold: f32) -> bool {
+        match self {
+            Comparison::Below => level < threshold,
+            Comparison::AtMost => level <= threshold,
+            Comparison::Above => level > threshold,
+            Comparison::AtLeast => level >= threshold,
+        }
+    }
+
+    /// Whether `a` is further past the threshold than `b`.
+    fn more_extreme(&self, a: f32, b: f32) -> bool {
+        match self {
+            Comparison::Below | Comparison::AtMost => a < b,
+            Comparison::Above | Comparison::AtLeast => a > b,
+        }
+    }
+}
+
+/// A tide alert condition such as `level < 0 ft between 06:00 and 20:00` or
+/// `high > 14 ft`.
+///
+/// Grammar: `<level|high|low> <op> <feet>[ft] [between HH:MM and HH:MM]`
+/// where `op` is one of `<`, `<=`, `>`, `>=`. Time windows are in station
+/// local time and may wrap past midnight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rule {
+    pub subject: Subject,
+    pub comparison: Comparison,
+    pub threshold: f32,
+    pub window: Option<(NaiveTime, NaiveTime)>,
+    text: String,
+}
+
+impl Rule {
+    fn in_window(&self, dt: DateTime<Utc>) -> bool {
+        let t = dt.time().with_second(0).unwrap();
+        match self.window {
+            None => true,
+            Some((start, end)) if start <= end => start <= t && t <= end,
+            Some((start, end)) => t >= start || t <= end,
+        }
+    }
+
+    fn matches(&self, dt: DateTime<Utc>, level: f32) -> bool {
+        self.in_window(dt) && self.comparison.holds(level, self.threshold)
+    }
+
+    /// Alerts for this rule at or after `from`.
+    pub fn evaluate(
+        &self,
+        tv: &[TidePoint],
+        from: DateTime<Utc>,
+    ) -> Result<Vec<Alert>, Box<dyn Error>> {
+        match self.subject {
+            Subject::High | Subject::Low => {
+                let tide = if self.subject == Subject::High {
+                    Tide::High
+                } else {
+                    Tide::Low
+                };
+                Ok(tv
+                    .iter()
+                    .filter(|t| t.tide() == tide && t.dt() >= from)
+                    .filter(|t| self.matches(t.dt(), t.level()))
+                    .map(|t| Alert {
+                        rule: self.text.clone(),
+                        start: t.dt(),
+                        end: t.dt(),
+                        level: t.level(),
+                    })
+                    .collect())
+            }
+            Subject::Level => self.evaluate_level(tv, from),
+        }
+    }
+
+    /// Walk the interpolated curve and report each stretch of time the
+    /// condition holds, along with the most extreme level reached.
+    fn evaluate_level(
+        &self,
+        tv: &[TidePoint],
+        from: DateTime<Utc>,
+    ) -> Result<Vec<Alert>, Box<dyn Error>> {
+        let (first, last) = match (tv.first(), tv.last()) {
+            (Some(f), Some(l)) => (f.dt(), l.dt()),
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut alerts = Vec::new();
+        let mut current: Option<Alert> = None;
+        let mut dt = if from > first { from } else { first };
+        while dt <= last {
+            let level = level_at(tv, dt)
+                .ok_or_else(|| format!("no tide predictions around {}, are they in order?", dt))?;
+            if self.matches(dt, level) {
+                let alert = current.get_or_insert_with(|| Alert {
+                    rule: self.text.clone(),
+                    start: dt,
+                    end: dt,
+                    level,
+                });
+                alert.end = dt;
+                if self.comparison.more_extreme(level, alert.level) {
+                    alert.level = level;
+                }
+            } else if let Some(alert) = current.take() {
+                alerts.push(alert);
+            }
+            dt += Duration::minutes(SAMPLE_MINUTES);
+        }
+        alerts.extend(current);
+        Ok(alerts)
+    }
+}
+
+fn parse_time(s: &str) -> Result<NaiveTime, Box<dyn Error>> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| format!("invalid time '{}'", s).into())
+}
+
+impl FromStr for Rule {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Rule, Box<dyn Error>> {
+        let lower = s.trim().to_lowercase();
+        // Let `14ft` and `14 ft` read the same.
+        let spaced = lower.replace("ft", " ft ");
+        let mut tokens = spaced.split_whitespace().peekable();
+
+        let subject = match tokens.next() {
+            Some("level") => Subject::Level,
+            Some("high") => Subject::High,
+            Some("low") => Subject::Low,
+            Some(other) => return Err(format!("unknown tide subject '{}'", other).into()),
+            None => return Err("empty rule".into()),
+        };
+        let comparison = match tokens.next() {
+            Some("<") => Comparison::Below,
+            Some("<=") => Comparison::AtMost,
+            Some(">") => Comparison::Above,
+            Some(">=") => Comparison::AtLeast,
+            Some(other) => return Err(format!("unknown comparison '{}'", other).into()),
+            None => return Err("missing comparison".into()),
+        };
+        let threshold = match tokens.next() {
+            Some(n) => n
+                .parse::<f32>()
+                .map_err(|_| format!("invalid threshold '{}'", n))?,
+            None => return Err("missing threshold".into()),
+        };
+        if tokens.peek() == Some(&"ft") {
+            tokens.next();
+        }
+
+        let window = match tokens.next() {
+            None => None,
+            Some("between") => {
+                let start = parse_time(tokens.next().ok_or("missing window start")?)?;
+                if tokens.next() != Some("and") {
+                    return Err("expected 'and' in time window".into());
+                }
+                let end = parse_time(tokens.next().ok_or("missing window end")?)?;
+                Some((start, end))
+            }
+            Some(other) => return Err(format!("unexpected '{}'", other).into()),
+        };
+        if let Some(extra) = tokens.next() {
+            return Err(format!("unexpected '{}'", extra).into());
+        }
+
+        Ok(Rule {
+            subject,
+            comparison,
+            threshold,
+            window,
+            text: s.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Parse one rule per line, skipping blank lines and `#` comments.
+pub fn parse_rules(raw: &str) -> Result<Vec<Rule>, Box<dyn Error>> {
+    raw.lines()
+        .enumerate()
+        .map(|(i, l)| (i, l.trim()))
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .map(|(i, l)| {
+            l.parse::<Rule>()
+                .map_err(|e| format!("rule line {}: {}", i + 1, e).into())
+        })
+        .collect()
+}
+
+/// An upcoming period during which a rule's condition holds. High and low
+/// rules produce an instant, with `start == end`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alert {
+    pub rule: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// The most extreme level reached during the alert, in feet.
+    pub level: f32,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.start.format("%Y-%m-%d %H:%M"))?;
+        if self.end != self.start {
+            write!(f, " - {}", self.end.format("%H:%M"))?;
+        }
+        write!(f, " ({:.1} ft)", self.level)
+    }
+}
+
+/// Evaluate every rule over `tv` from `from` onward, ordered by start time.
+pub fn evaluate(
+    rules: &[Rule],
+    tv: &[TidePoint],
+    from: DateTime<Utc>,
+) -> Result<Vec<Alert>, Box<dyn Error>> {
+    let mut alerts = Vec::new();
+    for rule in rules {
+        alerts.extend(rule.evaluate(tv, from)?);
+    }
+    alerts.sort_by_key(|a| a.start);
+    Ok(alerts)
+}
+
+#[derive(Serialize, Debug)]
+struct JsonAlert<'a> {
+    rule: &'a str,
+    start: String,
+    end: String,
+    level: f32,
+}
+
+/// Serialize alerts for the JS side. Times are written without an offset
+/// (`2022-01-10T05:05`) so `new Date()` reads them as local time.
+pub fn to_json(alerts: &[Alert]) -> String {
+    let json: Vec<JsonAlert> = alerts
+        .iter()
+        .map(|a| JsonAlert {
+            rule: &a.rule,
+            start: a.start.format("%Y-%m-%dT%H:%M").to_string(),
+            end: a.end.format("%Y-%m-%dT%H:%M").to_string(),
+            level: a.level,
+        })
+        .collect();
+    serde_json::to_string(&json).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alerts::*;
+    use crate::tides::{parse_noaa_time, Predictions};
+
+    const PREDICTIONS: &str = r#"{ "predictions" : [{"t":"2022-05-27 03:49", "v":"14.036", "type":"H"},{"t":"2022-05-27 11:02", "v":"-0.058", "type":"L"},{"t":"2022-05-27 17:51", "v":"12.111", "type":"H"},{"t":"2022-05-27 23:02", "v":"5.967", "type":"L"},{"t":"2022-05-28 04:17", "v":"13.733", "type":"H"},{"t":"2022-05-28 11:32", "v":"-0.922", "type":"L"},{"t":"2022-05-28 18:42", "v":"12.991", "type":"H"}]}"#;
+
+    fn tide_points() -> Vec<TidePoint> {
+        let p: Predictions = serde_json::from_str(PREDICTIONS).unwrap();
+        p.tide_points()
+    }
+
+    #[test]
+    fn parse_rule() {
+        let r: Rule = "Level < 0 ft between 06:00 and 20:00".parse().unwrap();
+        assert_eq!(r.subject, Subject::Level);
+        assert_eq!(r.comparison, Comparison::Below);
+        assert_eq!(r.threshold, 0.0);
+        assert!(r.window.is_some());
+
+        let r: Rule = "high >= 14ft".parse().unwrap();
+        assert_eq!(r.comparison, Comparison::AtLeast);
+        assert_eq!(r.threshold, 14.0);
+        assert_eq!(r.window, None);
+
+        assert!("tide < 0".parse::<Rule>().is_err());
+        assert!("low = 0".parse::<Rule>().is_err());
+        assert!("low < zero".parse::<Rule>().is_err());
+        assert!("low < 0 between 6 and 8".parse::<Rule>().is_err());
+        assert!("low < 0 ft at noon".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn parse_rules_reports_line() {
+        let err = parse_rules("# morning lows\nlow < 0\n\nhigh ~ 14\n").unwrap_err();
+        assert!(err.to_string().starts_with("rule line 4"));
+    }
+
+    #[test]
+    fn high_and_low_rules() {
+        let tv = tide_points();
+        let from = parse_noaa_time("2022-05-27 00:00").unwrap();
+        let rules = parse_rules("high > 13.5 ft\nlow < 0 between 11:15 and 12:00").unwrap();
+        let alerts = evaluate(&rules, &tv, from).unwrap();
+
+        let starts: Vec<String> = alerts
+            .iter()
+            .map(|a| a.start.format("%d %H:%M").to_string())
+            .collect();
+        assert_eq!(starts, vec!["27 03:49", "28 04:17", "28 11:32"]);
+        assert_eq!(alerts[2].level, -0.922);
+
+        // Alerts before `from` are not upcoming.
+        let later = parse_noaa_time("2022-05-28 00:00").unwrap();
+        assert_eq!(evaluate(&rules, &tv, later).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn level_rule_spans() {
+        let tv = tide_points();
+        let from = parse_noaa_time("2022-05-27 00:00").unwrap();
+        let rule: Rule = "level < 0".parse().unwrap();
+        let alerts = rule.evaluate(&tv, from).unwrap();
+
+        assert_eq!(alerts.len(), 2);
+        for (alert, low) in alerts.iter().zip(["2022-05-27 11:02", "2022-05-28 11:32"]) {
+            let low = parse_noaa_time(low).unwrap();
+            assert!(alert.start < low && low < alert.end);
+            assert!(alert.level < 0.0);
+        }
+        // The deeper low stays negative for longer.
+        assert!(alerts[1].end - alerts[1].start > alerts[0].end - alerts[0].start);
+
+        let windowed: Rule = "level < 0 between 11:30 and 23:00".parse().unwrap();
+        let alerts = windowed.evaluate(&tv, from).unwrap();
+        assert!(alerts
+            .iter()
+            .all(|a| a.start.time() >= parse_time("11:30").unwrap()));
+    }
+
+    #[test]
+    fn alert_json() {
+        let tv = tide_points();
+        let from = parse_noaa_time("2022-05-28 00:00").unwrap();
+        let alerts = evaluate(&["high > 13.5".parse().unwrap()], &tv, from).unwrap();
+        assert_eq!(
+            to_json(&alerts),
+            r#"[{"rule":"high > 13.5","start":"2022-05-28T04:17","end":"2022-05-28T04:17","level":13.733}]"#
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Render the fractal explorer to a PNG outside the browser and report how
+//! long the pixel buffer took, for benchmarking the renderer.
+//!
+//! Usage: `fractal-png <out.png> [WIDTHxHEIGHT] [re im zoom]`
+//!
+//! Defaults to the 800x600 home view of the Mandelbrot set.
+use herron_island::mandelbrot::{Fractal, View};
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process;
+use std::time::Instant;
+
+struct Config {
+    out: PathBuf,
+    size: (u32, u32),
+    target: Option<(f64, f64, f64)>,
+}
+
+impl Config {
+    fn new(mut args: env::Args) -> Result<Config, String> {
+        args.next(); // consume the binary name
+
+        let out = match args.next() {
+            Some(arg) => PathBuf::from(arg),
+            None => return Err(String::from("no output file parameter")),
+        };
+        let size = match args.next() {
+            Some(arg) => parse_size(&arg)?,
+            None => (800, 600),
+        };
+        let rest: Vec<String> = args.collect();
+        let target = match rest.as_slice() {
+            [] => None,
+            [re, im, zoom] => Some((parse_number(re)?, parse_number(im)?, parse_number(zoom)?)),
+            _ => return Err(String::from("expected re, im and zoom together")),
+        };
+
+        Ok(Config { out, size, target })
+    }
+}
+
+fn parse_size(arg: &str) -> Result<(u32, u32), String> {
+    let (w, h) = arg
+        .split_once('x')
+        .ok_or_else(|| format!("size '{}' is not WIDTHxHEIGHT", arg))?;
+    match (w.parse(), h.parse()) {
+        (Ok(w), Ok(h)) => Ok((w, h)),
+        _ => Err(format!("size '{}' is not WIDTHxHEIGHT", arg)),
+    }
+}
+
+fn parse_number(arg: &str) -> Result<f64, String> {
+    arg.parse()
+        .map_err(|_| format!("'{}' is not a number", arg))
+}
+
+fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let mut view = View::new(config.size.0, config.size.1, Fractal::Mandelbrot);
+    if let Some((re, im, zoom)) = config.target {
+        view.center_on(re, im);
+        let (w, h) = view.size();
+        view.zoom_at(w as i32 / 2, h as i32 / 2, zoom);
+    }
+
+    let start = Instant::now();
+    let pixels = view.render();
+    let elapsed = start.elapsed();
+    println!(
+        "{}x{} at zoom {:e} ({} iterations): {:.1} ms, {:.2} Mpixel/s",
+        config.size.0,
+        config.size.1,
+        view.zoom(),
+        view.max_iter(),
+        elapsed.as_secs_f64() * 1000.0,
+        (pixels.len() / 4) as f64 / elapsed.as_secs_f64() / 1e6
+    );
+
+    herron_island::mandelbrot::write_png(&config.out, view.size(), &pixels)?;
+    Ok(())
+}
+
+fn main() {
+    let config = Config::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("problem parsing arguments: {}", err);
+        process::exit(1);
+    });
+
+    if let Err(e) = run(config) {
+        eprintln!("application err: {}", e);
+        process::exit(1);
+    }
+}
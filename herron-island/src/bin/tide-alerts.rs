@@ -0,0 +1,100 @@
+//! Evaluate tide alert rules against a NOAA predictions file, for running
+//! from cron.
+//!
+//! Usage: `tide-alerts <predictions.json> <rules.txt> [mail spool]`
+//!
+//! Without a mail spool the alerts are printed to stdout. With one, they are
+//! appended as a single message in mbox format addressed to `$USER`, so the
+//! local mail system picks them up. Nothing is written when no alerts fire.
+use chrono::{Local, Utc};
+use herron_island::alerts::{self, Alert};
+use herron_island::tides::{station_now, Predictions};
+use std::env;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process;
+
+struct Config {
+    predictions: String,
+    rules: String,
+    spool: Option<String>,
+    to: String,
+}
+
+impl Config {
+    fn new(mut args: env::Args) -> Result<Config, &'static str> {
+        args.next(); // consume the binary name
+
+        let predictions = match args.next() {
+            Some(arg) => arg,
+            None => return Err("no predictions file parameter"),
+        };
+        let rules = match args.next() {
+            Some(arg) => arg,
+            None => return Err("no rules file parameter"),
+        };
+        let spool = args.next();
+        let to = env::var("USER").unwrap_or_else(|_| String::from("root"));
+
+        Ok(Config {
+            predictions,
+            rules,
+            spool,
+            to,
+        })
+    }
+}
+
+fn mbox_message(to: &str, alerts: &[Alert]) -> String {
+    let now = Local::now();
+    let mut msg = format!(
+        "From tide-alerts {}\nFrom: tide-alerts@localhost\nTo: {}\nDate: {}\nSubject: {} upcoming tide alert{}\n\n",
+        Utc::now().format("%a %b %e %H:%M:%S %Y"),
+        to,
+        now.to_rfc2822(),
+        alerts.len(),
+        if alerts.len() == 1 { "" } else { "s" },
+    );
+    for a in alerts {
+        // Escape body lines that would otherwise start a new mbox message.
+        let line = a.to_string();
+        if line.starts_with("From ") {
+            msg.push('>');
+        }
+        msg.push_str(&line);
+        msg.push('\n');
+    }
+    msg.push('\n');
+    msg
+}
+
+fn run(config: Config) -> Result<(), Box<dyn Error>> {
+    let raw = fs::read_to_string(&config.predictions)?;
+    let p: Predictions = serde_json::from_str(&raw)?;
+    let tv = p.tide_points();
+    let rules = alerts::parse_rules(&fs::read_to_string(&config.rules)?)?;
+
+    let found = alerts::evaluate(&rules, &tv, station_now())?;
+    match config.spool {
+        None => found.iter().for_each(|a| println!("{}", a)),
+        Some(_) if found.is_empty() => {}
+        Some(path) => {
+            let mut spool = OpenOptions::new().create(true).append(true).open(path)?;
+            spool.write_all(mbox_message(&config.to, &found).as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let config = Config::new(env::args()).unwrap_or_else(|err| {
+        eprintln!("problem parsing arguments: {}", err);
+        process::exit(1);
+    });
+
+    if let Err(e) = run(config) {
+        eprintln!("application err: {}", e);
+        process::exit(1);
+    }
+}
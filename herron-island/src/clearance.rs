@@ -0,0 +1,304 @@
+use crate::tides::{level_at, TidePoint};
+use crate::{CoordMap, DrawResult};
+use chrono::prelude::*;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+#[cfg(feature = "wasm")]
+use plotters_canvas::CanvasBackend;
+use serde::Deserialize;
+use std::error::Error;
+
+/// Minutes between clearance samples, matching the alert rules.
+const SAMPLE_MINUTES: i64 = 6;
+
+#[derive(Deserialize, Debug)]
+struct CsvDepth {
+    distance: f32,
+    depth: f32,
+}
+
+/// Charted depth at a point along the route.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthPoint {
+    /// Distance from the mainland landing, in feet.
+    pub distance: f32,
+    /// Depth below chart datum (MLLW), in feet. Drying heights are negative.
+    pub depth: f32,
+}
+
+/// Depth profile along the ferry route.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    points: Vec<DepthPoint>,
+}
+
+impl Profile {
+    pub fn new(mut points: Vec<DepthPoint>) -> Result<Profile, Box<dyn Error>> {
+        if points.len() < 2 {
+            return Err("depth profile needs at least two points".into());
+        }
+        points.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        Ok(Profile { points })
+    }
+
+    /// Parse a CSV with a `distance,depth` header, both in feet.
+    pub fn from_csv(raw: &str) -> Result<Profile, Box<dyn Error>> {
+        let mut points = Vec::new();
+        for (i, row) in csv::Reader::from_reader(raw.as_bytes())
+            .deserialize()
+            .enumerate()
+        {
+            let row: CsvDepth = row.map_err(|e| format!("profile row {}: {}", i + 1, e))?;
+            if !(row.distance.is_finite() && row.depth.is_finite()) {
+                return Err(format!("profile row {}: values must be finite", i + 1).into());
+            }
+            points.push(DepthPoint {
+                distance: row.distance,
+                depth: row.depth,
+            });
+        }
+        Profile::new(points)
+    }
+
+    /// The shallowest point on the route, which sets the clearance.
+    pub fn controlling(&self) -> DepthPoint {
+        *self
+            .points
+            .iter()
+            .min_by(|a, b| a.depth.total_cmp(&b.depth))
+            .expect("profile has points")
+    }
+
+    /// Clearance under a keel of `draft` feet at each profile point, with
+    /// the tide at `level` feet above chart datum.
+    pub fn clearance_along(&self, level: f32, draft: f32) -> Vec<(f32, f32)> {
+        self.points
+            .iter()
+            .map(|p| (p.distance, p.depth + level - draft))
+            .collect()
+    }
+
+    /// Least clearance anywhere on the route at `dt`, or `None` outside the
+    /// predicted range.
+    pub fn clearance_at(&self, tv: &[TidePoint], dt: DateTime<Utc>, draft: f32) -> Option<f32> {
+        level_at(tv, dt).map(|level| self.controlling().depth + level - draft)
+    }
+
+    /// Least clearance on the route sampled every few minutes from `from`
+    /// to `to`.
+    pub fn clearance_series(
+        &self,
+        tv: &[TidePoint],
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        draft: f32,
+    ) -> Vec<(DateTime<Utc>, f32)> {
+        let mut series = Vec::new();
+        let mut dt = from;
+        while dt <= to {
+            if let Some(c) = self.clearance_at(tv, dt, draft) {
+                series.push((dt, c));
+            }
+            dt += Duration::minutes(SAMPLE_MINUTES);
+        }
+        series
+    }
+}
+
+/// Draw today's under-keel clearance for a vessel of `draft` feet on the
+/// canvas with id `canvas_id`.
+#[cfg(feature = "wasm")]
+pub fn draw(
+    canvas_id: &str,
+    tv: Vec<TidePoint>,
+    profile: &Profile,
+    draft: f32,
+) -> DrawResult<impl CoordMap<f32>> {
+    let backend = CanvasBackend::new(canvas_id).expect("cannot find canvas");
+    draw_on(
+        backend.into_drawing_area(),
+        chrono::Local::now().naive_local(),
+        tv,
+        profile,
+        draft,
+    )
+}
+
+/// Draw the clearance chart for the day of `now`, a local wall clock time,
+/// on any plotters backend. Laid out like `tides::draw_on`: hours of the day
+/// along x, with the time of `now` marked.
+pub fn draw_on<DB>(
+    root: DrawingArea<DB, Shift>,
+    now: NaiveDateTime,
+    tv: Vec<TidePoint>,
+    profile: &Profile,
+    draft: f32,
+) -> DrawResult<impl CoordMap<f32>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let label_style = TextStyle::from(("sans-serif", 10).into_font()).color(&WHITE);
+    root.fill(&RGBColor(54, 95, 145))?;
+
+    let neh = now.date().and_hms_opt(0, 0, 0).unwrap();
+    let today = Utc.from_utc_datetime(&neh);
+
+    let hours = |dt: DateTime<Utc>| (dt - today).num_seconds() as f32 / 3600f32;
+    let xys: Vec<(f32, f32)> = profile
+        .clearance_series(&tv, today, today + Duration::days(1), draft)
+        .into_iter()
+        .map(|(dt, c)| (hours(dt), c))
+        .collect();
+    if xys.is_empty() {
+        return Err(format!("no tide predictions for {}", today.format("%Y-%m-%d")).into());
+    }
+
+    // Always show the keel line at zero clearance.
+    let (lo, hi) = xys
+        .iter()
+        .fold((0f32, 0f32), |(lo, hi), &(_, c)| (lo.min(c), hi.max(c)));
+    let pad = ((hi - lo) * 0.1).max(1f32);
+    let (chart_bottom, chart_top) = (lo - pad, hi + pad);
+
+    let controlling = profile.controlling();
+    let mut chart = ChartBuilder::on(&root)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0f32..24f32, chart_bottom..chart_top)?;
+
+    chart
+        .configure_mesh()
+        .x_labels(5)
+        .x_label_style(&WHITE)
+        .x_desc(format!(
+            "{} Clearance for {:.1} ft draft, {:.1} ft charted at {:.0} ft",
+            today.format("%Y-%m-%d"),
+            draft,
+            controlling.depth,
+            controlling.distance
+        ))
+        .y_labels(10)
+        .y_label_style(label_style)
+        .y_desc("Under-keel Clearance")
+        .draw()?;
+
+    // Water under the keel, and in red where the vessel would touch bottom.
+    chart.draw_series(AreaSeries::new(
+        xys.iter().map(|&(x, c)| (x, c.max(0f32))),
+        0f32,
+        RGBColor(139, 166, 214).mix(0.5),
+    ))?;
+    chart.draw_series(AreaSeries::new(
+        xys.iter().map(|&(x, c)| (x, c.min(0f32))),
+        0f32,
+        RGBColor(214, 69, 65).mix(0.8),
+    ))?;
+    chart.draw_series(LineSeries::new(
+        xys.iter().copied(),
+        RGBColor(206, 222, 248).stroke_width(1),
+    ))?;
+    chart.draw_series(LineSeries::new(
+        vec![(0f32, 0f32), (24f32, 0f32)],
+        WHITE.stroke_width(1),
+    ))?;
+
+    // Draw vertical line to show current time
+    let x_val = now.hour() as f32 + (now.minute() as f32 / 60f32);
+    let x_split = 0.05f32;
+    if let Some(&(_, c)) = xys.iter().find(|(x, _)| *x >= x_val) {
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(x_val - x_split, chart_bottom), (x_val + x_split, c)],
+            RGBColor(54, 200, 95).filled(),
+        )))?;
+    }
+
+    root.present()?;
+    Ok(chart.into_coord_trans())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clearance::*;
+    use crate::tides::{parse_noaa_time, Tide};
+
+    const ROUTE: &str = "distance,depth
+1200,9.5
+0,4.0
+600,2.5
+";
+
+    fn tides() -> Vec<TidePoint> {
+        vec![
+            TidePoint::new(
+                parse_noaa_time("2022-05-28 03:00").unwrap(),
+                -1.5,
+                Tide::Low,
+            ),
+            TidePoint::new(
+                parse_noaa_time("2022-05-28 09:00").unwrap(),
+                12.5,
+                Tide::High,
+            ),
+            TidePoint::new(parse_noaa_time("2022-05-28 15:00").unwrap(), 1.0, Tide::Low),
+        ]
+    }
+
+    #[test]
+    fn profile_csv() {
+        let p = Profile::from_csv(ROUTE).unwrap();
+        assert_eq!(p.points[0].distance, 0f32);
+        assert_eq!(
+            p.controlling(),
+            DepthPoint {
+                distance: 600f32,
+                depth: 2.5
+            }
+        );
+        assert_eq!(
+            p.clearance_along(2.0, 5.0),
+            vec![(0f32, 1.0), (600f32, -0.5), (1200f32, 6.5)]
+        );
+
+        let err = Profile::from_csv("distance,depth\n0,deep\n").unwrap_err();
+        assert!(err.to_string().starts_with("profile row 1: "));
+        assert!(Profile::from_csv("distance,depth\n0,4\n").is_err());
+    }
+
+    #[test]
+    fn clearance_follows_the_tide() {
+        let p = Profile::from_csv(ROUTE).unwrap();
+        let tv = tides();
+        let at = |s| p.clearance_at(&tv, parse_noaa_time(s).unwrap(), 6.0);
+
+        assert_eq!(at("2022-05-28 09:00"), Some(2.5 + 12.5 - 6.0));
+        assert_eq!(at("2022-05-28 03:00"), Some(2.5 - 1.5 - 6.0));
+        assert_eq!(at("2022-05-28 02:00"), None);
+
+        let from = parse_noaa_time("2022-05-28 00:00").unwrap();
+        let to = parse_noaa_time("2022-05-29 00:00").unwrap();
+        let series = p.clearance_series(&tv, from, to, 6.0);
+        // 03:00 through 15:00 inclusive, every 6 minutes.
+        assert_eq!(series.len(), 12 * 10 + 1);
+        assert!(series.iter().any(|&(_, c)| c < 0f32));
+        assert!(series.iter().any(|&(_, c)| c > 0f32));
+    }
+
+    #[test]
+    fn draws_clearance_chart() {
+        let p = Profile::from_csv(ROUTE).unwrap();
+        let now = NaiveDate::from_ymd_opt(2022, 5, 28)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap();
+        let mut buf = vec![0u8; 400 * 300 * 3];
+        let root = BitMapBackend::with_buffer(&mut buf, (400, 300)).into_drawing_area();
+        assert!(draw_on(root, now, tides(), &p, 6.0).is_ok());
+
+        let root = BitMapBackend::with_buffer(&mut buf, (400, 300)).into_drawing_area();
+        let later = now + Duration::days(3);
+        assert!(draw_on(root, later, tides(), &p, 6.0).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Double-double number: an unevaluated sum `hi + lo` of two f64s, giving
+/// about 32 significant digits. Used to keep deep fractal zooms from running
+/// out of f64 precision.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+// Error free transformations, see Shewchuk and the QD library.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    pub fn new(hi: f64, lo: f64) -> DoubleDouble {
+        let (hi, lo) = quick_two_sum(hi, lo);
+        DoubleDouble { hi, lo }
+    }
+
+    /// Nearest f64.
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    pub fn sqr(self) -> DoubleDouble {
+        self * self
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(x: f64) -> DoubleDouble {
+        DoubleDouble { hi: x, lo: 0.0 }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn add(self, rhs: DoubleDouble) -> DoubleDouble {
+        let (s, e) = two_sum(self.hi, rhs.hi);
+        let (t, f) = two_sum(self.lo, rhs.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        DoubleDouble::new(s, e + f)
+    }
+}
+
+impl Add<f64> for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn add(self, rhs: f64) -> DoubleDouble {
+        let (s, e) = two_sum(self.hi, rhs);
+        DoubleDouble::new(s, e + self.lo)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn neg(self) -> DoubleDouble {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn sub(self, rhs: DoubleDouble) -> DoubleDouble {
+        self + -rhs
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn mul(self, rhs: DoubleDouble) -> DoubleDouble {
+        let (p, e) = two_prod(self.hi, rhs.hi);
+        DoubleDouble::new(p, e + (self.hi * rhs.lo + self.lo * rhs.hi))
+    }
+}
+
+impl Mul<f64> for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn mul(self, rhs: f64) -> DoubleDouble {
+        let (p, e) = two_prod(self.hi, rhs);
+        DoubleDouble::new(p, e + self.lo * rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dd::*;
+
+    #[test]
+    fn keeps_digits_f64_loses() {
+        let one = DoubleDouble::from(1.0);
+        let tiny = DoubleDouble::from(1e-20);
+        assert_eq!(1.0 + 1e-20 - 1.0, 0.0);
+        assert_eq!(((one + tiny) - one).to_f64(), 1e-20);
+        assert_eq!((one + 1e-20 + -1.0).to_f64(), 1e-20);
+    }
+
+    #[test]
+    fn multiplication() {
+        // (1 + 2^-60)^2 = 1 + 2^-59 + 2^-120
+        let x = DoubleDouble::new(1.0, 2f64.powi(-60));
+        let sq = x.sqr();
+        assert_eq!(sq.hi, 1.0);
+        assert_eq!(sq.lo, 2f64.powi(-59));
+        assert_eq!((x * 3.0).lo, 3.0 * 2f64.powi(-60));
+        assert_eq!((-x * x).hi, -1.0);
+    }
+}
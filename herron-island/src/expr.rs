@@ -0,0 +1,525 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Deepest expression tree the parser builds, counting parentheses, calls,
+/// signs, powers and each operator in a chain like `x + x + x`. Parsing,
+/// evaluating and dropping all recurse, so this keeps them well within the
+/// small wasm stack.
+const MAX_DEPTH: usize = 256;
+
+/// Binary arithmetic operators.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+/// Built-in functions callable from an expression.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Log,
+    Sqrt,
+    Abs,
+    Pow,
+}
+
+impl Func {
+    fn lookup(name: &str) -> Option<Func> {
+        match name {
+            "sin" => Some(Func::Sin),
+            "cos" => Some(Func::Cos),
+            "tan" => Some(Func::Tan),
+            "exp" => Some(Func::Exp),
+            "log" | "ln" => Some(Func::Log),
+            "sqrt" => Some(Func::Sqrt),
+            "abs" => Some(Func::Abs),
+            "pow" => Some(Func::Pow),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Func::Sin => "sin",
+            Func::Cos => "cos",
+            Func::Tan => "tan",
+            Func::Exp => "exp",
+            Func::Log => "log",
+            Func::Sqrt => "sqrt",
+            Func::Abs => "abs",
+            Func::Pow => "pow",
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Func::Pow => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Parsed arithmetic expression such as `2*sin(x)^2 + pow(x, 3)`.
+///
+/// Supports `+ - * / ^`, unary minus, parentheses, the functions `sin`,
+/// `cos`, `tan`, `exp`, `log` (natural, also `ln`), `sqrt`, `abs`,
+/// `pow(a, b)`, the constants `pi` and `e`, and named variables. `^` binds
+/// tighter than unary minus and is right associative, so `-x^2^3` is
+/// `-(x^(2^3))`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+/// Why an expression could not be evaluated.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvalError {
+    UnknownVariable(String),
+    /// The operation has no real result for its arguments, e.g. `log(-1)`.
+    Domain(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalError::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            EvalError::Domain(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for EvalError {}
+
+impl FromStr for Expr {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Expr, Box<dyn Error>> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.expr()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some((col, token)) => Err(format!("unexpected {} at column {}", token, col).into()),
+        }
+    }
+}
+
+impl Expr {
+    /// Evaluate with `vars` giving the value of each named variable.
+    pub fn eval(&self, vars: &[(&str, f64)]) -> Result<f64, EvalError> {
+        let value = match self {
+            Expr::Number(n) => *n,
+            Expr::Var(name) => match vars.iter().find(|(v, _)| v == name) {
+                Some((_, value)) => *value,
+                None => match name.as_str() {
+                    "pi" => std::f64::consts::PI,
+                    "e" => std::f64::consts::E,
+                    _ => return Err(EvalError::UnknownVariable(name.clone())),
+                },
+            },
+            Expr::Neg(a) => -a.eval(vars)?,
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (a.eval(vars)?, b.eval(vars)?);
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div if b == 0.0 => {
+                        return Err(EvalError::Domain(String::from("division by zero")))
+                    }
+                    Op::Div => a / b,
+                    Op::Pow => power(a, b)?,
+                }
+            }
+            Expr::Call(func, args) => {
+                let a = args[0].eval(vars)?;
+                match func {
+                    Func::Sin => a.sin(),
+                    Func::Cos => a.cos(),
+                    Func::Tan => a.tan(),
+                    Func::Exp => a.exp(),
+                    Func::Log if a <= 0.0 => {
+                        return Err(EvalError::Domain(format!("log of non-positive {}", a)))
+                    }
+                    Func::Log => a.ln(),
+                    Func::Sqrt if a < 0.0 => {
+                        return Err(EvalError::Domain(format!("sqrt of negative {}", a)))
+                    }
+                    Func::Sqrt => a.sqrt(),
+                    Func::Abs => a.abs(),
+                    Func::Pow => power(a, args[1].eval(vars)?)?,
+                }
+            }
+        };
+        if value.is_finite() {
+            Ok(value)
+        } else {
+            Err(EvalError::Domain(String::from("result is not finite")))
+        }
+    }
+
+    /// Names of the variables the expression refers to, excluding the
+    /// constants `pi` and `e`, in order of first use.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_variables(&mut names);
+        names
+    }
+
+    fn collect_variables<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Var(name) => {
+                if name != "pi" && name != "e" && !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+            Expr::Neg(a) => a.collect_variables(names),
+            Expr::Binary(_, a, b) => {
+                a.collect_variables(names);
+                b.collect_variables(names);
+            }
+            Expr::Call(_, args) => args.iter().for_each(|a| a.collect_variables(names)),
+        }
+    }
+}
+
+fn power(a: f64, b: f64) -> Result<f64, EvalError> {
+    if a < 0.0 && b.fract() != 0.0 {
+        return Err(EvalError::Domain(format!(
+            "{}^{} is not a real number",
+            a, b
+        )));
+    }
+    if a == 0.0 && b < 0.0 {
+        return Err(EvalError::Domain(String::from("division by zero")));
+    }
+    Ok(a.powf(b))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Symbol(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Symbol(c) => write!(f, "'{}'", c),
+        }
+    }
+}
+
+/// Split into tokens, each paired with its 1-based column.
+fn tokenize(s: &str) -> Result<Vec<(usize, Token)>, Box<dyn Error>> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c.is_ascii_digit() || c == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, as in 1.5e-3.
+            if i + 1 < chars.len()
+                && (chars[i] == 'e' || chars[i] == 'E')
+                && (chars[i + 1].is_ascii_digit()
+                    || (chars[i + 1] == '-' || chars[i + 1] == '+')
+                        && chars.get(i + 2).is_some_and(char::is_ascii_digit))
+            {
+                i += 2;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let n: String = chars[start..i].iter().collect();
+            let n = n
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number '{}' at column {}", n, start + 1))?;
+            tokens.push((start + 1, Token::Number(n)));
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let name = chars[start..i].iter().collect();
+            tokens.push((start + 1, Token::Ident(name)));
+        } else if "+-*/^(),".contains(c) {
+            i += 1;
+            tokens.push((start + 1, Token::Symbol(c)));
+        } else {
+            return Err(format!("unexpected '{}' at column {}", c, start + 1).into());
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser, one method per precedence level.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// Depth of the tree being built, see `descend`.
+    depth: usize,
+}
+
+impl Parser {
+    /// Go one level deeper, failing past `MAX_DEPTH` rather than
+    /// overflowing the stack later.
+    fn descend(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!(
+                "expression is too long or nested too deeply, over {} levels",
+                MAX_DEPTH
+            )
+            .into());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Run `parse` one level deeper.
+    fn nested(
+        &mut self,
+        parse: fn(&mut Parser) -> Result<Expr, Box<dyn Error>>,
+    ) -> Result<Expr, Box<dyn Error>> {
+        self.descend()?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if let Some((_, Token::Symbol(c))) = self.peek() {
+            if *c == symbol {
+                self.pos += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), Box<dyn Error>> {
+        if self.eat(symbol) {
+            return Ok(());
+        }
+        Err(match self.peek() {
+            Some((col, token)) => {
+                format!(
+                    "expected '{}' but found {} at column {}",
+                    symbol, token, col
+                )
+            }
+            None => format!("expected '{}' but the expression ended", symbol),
+        }
+        .into())
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Result<Expr, Box<dyn Error>> {
+        // Each operator puts the chain so far a level deeper.
+        let depth = self.depth;
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                self.depth = depth;
+                return Ok(lhs);
+            };
+            self.descend()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expr, Box<dyn Error>> {
+        // Each operator puts the chain so far a level deeper.
+        let depth = self.depth;
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else {
+                self.depth = depth;
+                return Ok(lhs);
+            };
+            self.descend()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Expr, Box<dyn Error>> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.nested(Parser::unary)?)))
+        } else if self.eat('+') {
+            self.nested(Parser::unary)
+        } else {
+            self.power()
+        }
+    }
+
+    // power := atom ('^' unary)?
+    fn power(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            Ok(Expr::Binary(
+                Op::Pow,
+                Box::new(base),
+                Box::new(self.nested(Parser::unary)?),
+            ))
+        } else {
+            Ok(base)
+        }
+    }
+
+    // atom := number | name | name '(' args ')' | '(' expr ')'
+    fn atom(&mut self) -> Result<Expr, Box<dyn Error>> {
+        let (col, token) = match self.peek() {
+            Some(t) => t.clone(),
+            None => return Err("expression ended unexpectedly".into()),
+        };
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Ident(name) => {
+                if !self.eat('(') {
+                    return Ok(Expr::Var(name));
+                }
+                let func = Func::lookup(&name)
+                    .ok_or_else(|| format!("unknown function '{}' at column {}", name, col))?;
+                let mut args = vec![self.nested(Parser::expr)?];
+                while self.eat(',') {
+                    args.push(self.nested(Parser::expr)?);
+                }
+                self.expect(')')?;
+                if args.len() != func.arity() {
+                    return Err(format!(
+                        "{} takes {} argument(s) but was given {} at column {}",
+                        func.name(),
+                        func.arity(),
+                        args.len(),
+                        col
+                    )
+                    .into());
+                }
+                Ok(Expr::Call(func, args))
+            }
+            Token::Symbol('(') => {
+                let inner = self.nested(Parser::expr)?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            token => Err(format!("unexpected {} at column {}", token, col).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expr::*;
+
+    fn eval(s: &str, x: f64) -> Result<f64, EvalError> {
+        s.parse::<Expr>().unwrap().eval(&[("x", x)])
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3", 0.0), Ok(7.0));
+        assert_eq!(eval("(1 + 2) * 3", 0.0), Ok(9.0));
+        assert_eq!(eval("8 / 4 / 2", 0.0), Ok(1.0));
+        assert_eq!(eval("2^3^2", 0.0), Ok(512.0));
+        assert_eq!(eval("-x^2", 3.0), Ok(-9.0));
+        assert_eq!(eval("2^-1", 0.0), Ok(0.5));
+        assert_eq!(
+            eval("1.5e2 + 2*e", 0.0),
+            Ok(150.0 + 2.0 * std::f64::consts::E)
+        );
+    }
+
+    #[test]
+    fn functions_and_variables() {
+        assert_eq!(eval("sin(pi / 2) + cos(0)", 0.0), Ok(2.0));
+        assert_eq!(eval("exp(log(x))", 5.0).map(|v| v.round()), Ok(5.0));
+        assert_eq!(eval("pow(x, 3) - sqrt(abs(-16))", 2.0), Ok(4.0));
+
+        let e: Expr = "x * z + pi * y - x".parse().unwrap();
+        assert_eq!(e.variables(), vec!["x", "z", "y"]);
+        assert_eq!(
+            e.eval(&[("x", 1.0)]),
+            Err(EvalError::UnknownVariable(String::from("z")))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |s: &str| s.parse::<Expr>().unwrap_err().to_string();
+        assert_eq!(err("1 +"), "expression ended unexpectedly");
+        assert_eq!(err("(1 + 2"), "expected ')' but the expression ended");
+        assert_eq!(err("2 * * 3"), "unexpected '*' at column 5");
+        assert_eq!(err("x $ 2"), "unexpected '$' at column 3");
+        assert_eq!(err("1 2"), "unexpected number 2 at column 3");
+        assert_eq!(err("foo(x)"), "unknown function 'foo' at column 1");
+        assert_eq!(
+            err("pow(x)"),
+            "pow takes 2 argument(s) but was given 1 at column 1"
+        );
+        assert_eq!(err("1..2"), "invalid number '1..2' at column 1");
+
+        // Deep trees are refused instead of overflowing the stack.
+        let too_deep = "expression is too long or nested too deeply, over 256 levels";
+        let parens = |n| "(".repeat(n) + "x" + &")".repeat(n);
+        assert_eq!(err(&parens(100_000)), too_deep);
+        assert_eq!(err(&"-".repeat(100_000)), too_deep);
+        assert_eq!(err(&"x^".repeat(100_000)), too_deep);
+        assert_eq!(err(&("x+".repeat(100_000) + "x")), too_deep);
+        assert_eq!(err(&("x*".repeat(100_000) + "x")), too_deep);
+        assert_eq!(eval(&parens(200), 2.0), Ok(2.0));
+        assert_eq!(eval(&("x+".repeat(200) + "x"), 1.0), Ok(201.0));
+    }
+
+    #[test]
+    fn domain_errors() {
+        let domain = |s: &str, x: f64| match eval(s, x) {
+            Err(EvalError::Domain(msg)) => msg,
+            other => panic!("expected domain error, got {:?}", other),
+        };
+        assert_eq!(domain("log(x)", 0.0), "log of non-positive 0");
+        assert_eq!(domain("sqrt(x)", -4.0), "sqrt of negative -4");
+        assert_eq!(domain("1 / x", 0.0), "division by zero");
+        assert_eq!(domain("x^0.5", -1.0), "-1^0.5 is not a real number");
+        assert_eq!(domain("exp(x)", 1000.0), "result is not finite");
+        assert_eq!(eval("x^3", -2.0), Ok(-8.0));
+    }
+}
@@ -1,31 +1,269 @@
-use crate::DrawResult;
+use crate::expr::{EvalError, Expr};
+use crate::{CoordMap, DrawResult};
+use plotters::coord::Shift;
 use plotters::prelude::*;
+#[cfg(feature = "wasm")]
 use plotters_canvas::CanvasBackend;
+use std::error::Error;
+use std::ops::Range;
+use std::str::FromStr;
 
-/// Draw power function f(x) = x^power.
-pub fn draw(canvas_id: &str, power: i32) -> DrawResult<impl Fn((i32, i32)) -> Option<(f32, f32)>> {
+/// Points evaluated per function across the x range.
+const SAMPLES: usize = 500;
+
+/// A function of `x` to plot, keeping the text it was parsed from for the
+/// caption and legend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub text: String,
+    pub expr: Expr,
+}
+
+impl FromStr for Function {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Function, Box<dyn Error>> {
+        let text = s.trim();
+        // Accept "y = ..." and "f(x) = ..." as well as a bare expression.
+        let text = match text.split_once('=') {
+            Some((_, rhs)) => rhs.trim(),
+            None => text,
+        };
+        let expr: Expr = text.parse()?;
+        if let Some(v) = expr.variables().into_iter().find(|v| *v != "x") {
+            return Err(format!("unknown variable '{}', functions may only use x", v).into());
+        }
+        Ok(Function {
+            text: text.to_string(),
+            expr,
+        })
+    }
+}
+
+/// Parse one function per line or `;`-separated, skipping blank entries.
+pub fn parse_functions(raw: &str) -> Result<Vec<Function>, Box<dyn Error>> {
+    let mut functions = Vec::new();
+    for (i, s) in raw
+        .split(['\n', ';'])
+        .filter(|s| !s.trim().is_empty())
+        .enumerate()
+    {
+        let f = s
+            .parse::<Function>()
+            .map_err(|e| format!("function {}: {}", i + 1, e))?;
+        functions.push(f);
+    }
+    if functions.is_empty() {
+        return Err("no function to plot".into());
+    }
+    Ok(functions)
+}
+
+/// Evaluate `f` across `x_range`, splitting the curve wherever it is
+/// undefined so e.g. `log(x)` over `-1..1` starts at the y axis instead of
+/// failing the whole plot. Errors only if `f` is undefined everywhere.
+fn sample(f: &Function, x_range: &Range<f32>) -> DrawResult<Vec<Vec<(f32, f32)>>> {
+    let mut segments = Vec::new();
+    let mut current = Vec::new();
+    let mut first_error = None;
+    for i in 0..=SAMPLES {
+        let x = x_range.start + (x_range.end - x_range.start) * i as f32 / SAMPLES as f32;
+        // Finite in f64 can still overflow f32, e.g. exp(100).
+        let y = f
+            .expr
+            .eval(&[("x", x as f64)])
+            .and_then(|y| match y as f32 {
+                y if y.is_finite() => Ok(y),
+                _ => Err(EvalError::Domain(format!("{} is too large to plot", y))),
+            });
+        match y {
+            Ok(y) => current.push((x, y)),
+            Err(e) => {
+                first_error.get_or_insert(e);
+                if !current.is_empty() {
+                    segments.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    }
+    match (segments.is_empty(), first_error) {
+        (true, Some(e)) => Err(format!(
+            "y = {} is undefined on {}..{}: {}",
+            f.text, x_range.start, x_range.end, e
+        )
+        .into()),
+        _ => Ok(segments),
+    }
+}
+
+/// Smallest y range showing every sampled point, with a little headroom.
+fn fit_y(samples: &[Vec<Vec<(f32, f32)>>]) -> Range<f32> {
+    let ys = samples.iter().flatten().flatten().map(|&(_, y)| y);
+    let (lo, hi) = ys.fold((f32::MAX, f32::MIN), |(lo, hi), y| (lo.min(y), hi.max(y)));
+    if hi - lo < f32::EPSILON * hi.abs().max(1.0) {
+        return lo - 1.0..hi + 1.0;
+    }
+    let pad = (hi - lo) * 0.1;
+    lo - pad..hi + pad
+}
+
+/// Draw `functions` on the canvas with id `canvas_id`. Without a `y_range`
+/// the y axis is fitted to the curves.
+#[cfg(feature = "wasm")]
+pub fn draw(
+    canvas_id: &str,
+    functions: &[Function],
+    x_range: Range<f32>,
+    y_range: Option<Range<f32>>,
+) -> DrawResult<impl CoordMap<f32>> {
     let backend = CanvasBackend::new(canvas_id).expect("cannot find canvas");
-    let root = backend.into_drawing_area();
+    draw_on(backend.into_drawing_area(), functions, x_range, y_range)
+}
+
+/// `draw` onto any plotters drawing area.
+pub fn draw_on<DB>(
+    root: DrawingArea<DB, Shift>,
+    functions: &[Function],
+    x_range: Range<f32>,
+    y_range: Option<Range<f32>>,
+) -> DrawResult<impl CoordMap<f32>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    if x_range.start >= x_range.end || !(x_range.end - x_range.start).is_finite() {
+        return Err(format!("empty x range {}..{}", x_range.start, x_range.end).into());
+    }
+    if let Some(y) = &y_range {
+        if y.start >= y.end {
+            return Err(format!("empty y range {}..{}", y.start, y.end).into());
+        }
+    }
+    let samples = functions
+        .iter()
+        .map(|f| sample(f, &x_range))
+        .collect::<DrawResult<Vec<_>>>()?;
+    let y_range = y_range.unwrap_or_else(|| fit_y(&samples));
+
     let font: FontDesc = ("sans-serif", 20.0).into();
 
     root.fill(&WHITE)?;
 
-    let mut chart = ChartBuilder::on(&root)
+    let mut builder = ChartBuilder::on(&root);
+    builder
         .margin(20)
-        .caption(format!("y=x^{}", power), font)
         .x_label_area_size(30)
-        .y_label_area_size(30)
-        .build_cartesian_2d(-1f32..1f32, -1.2f32..1.2f32)?;
+        .y_label_area_size(30);
+    if let [f] = functions {
+        builder.caption(format!("y={}", f.text), font);
+    }
+    let mut chart = builder.build_cartesian_2d(x_range, y_range)?;
 
     chart.configure_mesh().x_labels(3).y_labels(3).draw()?;
 
-    chart.draw_series(LineSeries::new(
-        (-50..=50)
-            .map(|x| x as f32 / 50.0)
-            .map(|x| (x, x.powf(power as f32))),
-        &RED,
-    ))?;
+    for (i, (f, segments)) in functions.iter().zip(samples).enumerate() {
+        let color = if functions.len() == 1 {
+            RED.to_rgba()
+        } else {
+            Palette99::pick(i).to_rgba()
+        };
+        for (j, segment) in segments.into_iter().enumerate() {
+            let series = chart.draw_series(LineSeries::new(segment, &color))?;
+            // One legend entry per function, not per segment.
+            if j == 0 {
+                series
+                    .label(f.text.as_str())
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            }
+        }
+    }
+
+    if functions.len() > 1 {
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+    }
 
     root.present()?;
-    return Ok(chart.into_coord_trans());
-}
\ No newline at end of file
+    Ok(chart.into_coord_trans())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::func_plot::*;
+
+    #[test]
+    fn parse_function_lists() {
+        let fs = parse_functions("y = sin(x)\n\nf(x) = x^2; cos(x)").unwrap();
+        let texts: Vec<&str> = fs.iter().map(|f| f.text.as_str()).collect();
+        assert_eq!(texts, vec!["sin(x)", "x^2", "cos(x)"]);
+
+        let err = parse_functions("x; x * t").unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "function 2: unknown variable 't', functions may only use x"
+        );
+        assert!(parse_functions("x +")
+            .unwrap_err()
+            .to_string()
+            .starts_with("function 1: "));
+        assert!(parse_functions(" ; \n").is_err());
+    }
+
+    #[test]
+    fn undefined_points_split_the_curve() {
+        let f: Function = "1 / x".parse().unwrap();
+        let segments = sample(&f, &(-1.0..1.0)).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].len() + segments[1].len(), SAMPLES);
+
+        let f: Function = "log(x)".parse().unwrap();
+        let segments = sample(&f, &(-1.0..1.0)).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].iter().all(|&(x, _)| x > 0.0));
+
+        let err = sample(&f, &(-2.0..-1.0)).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "y = log(x) is undefined on -2..-1: log of non-positive -2"
+        );
+
+        // exp(x) outgrows f32 long before f64.
+        let f: Function = "exp(x)".parse().unwrap();
+        let segments = sample(&f, &(0.0..100.0)).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].iter().all(|&(_, y)| y.is_finite()));
+        assert!(fit_y(&[segments]).end.is_finite());
+        assert!(sample(&f, &(90.0..100.0)).is_err());
+    }
+
+    #[test]
+    fn fitted_range() {
+        let f: Function = "x^2".parse().unwrap();
+        let y = fit_y(&[sample(&f, &(-2.0..2.0)).unwrap()]);
+        assert!(y.start < 0.0 && y.start > -1.0);
+        assert!(y.end > 4.0 && y.end < 5.0);
+
+        let flat: Function = "3".parse().unwrap();
+        assert_eq!(fit_y(&[sample(&flat, &(0.0..1.0)).unwrap()]), 2.0..4.0);
+    }
+
+    #[test]
+    fn draws_several_functions() {
+        let fs = parse_functions("sin(x); cos(x); sqrt(x)").unwrap();
+        let mut buf = vec![0u8; 400 * 300 * 3];
+        let root = BitMapBackend::with_buffer(&mut buf, (400, 300)).into_drawing_area();
+        assert!(draw_on(root, &fs, -3.0..3.0, None).is_ok());
+
+        let root = BitMapBackend::with_buffer(&mut buf, (400, 300)).into_drawing_area();
+        assert!(draw_on(root, &fs, 1.0..1.0, None).is_err());
+        let root = BitMapBackend::with_buffer(&mut buf, (400, 300)).into_drawing_area();
+        assert!(draw_on(root, &fs, 0.0..1.0, Some(1.0..-1.0)).is_err());
+    }
+}
@@ -1,12 +1,37 @@
+use crate::dd::DoubleDouble;
+#[cfg(feature = "wasm")]
+use crate::CoordMap;
 use crate::DrawResult;
 use plotters::prelude::*;
+#[cfg(feature = "wasm")]
 use plotters_canvas::CanvasBackend;
+use std::error::Error;
 use std::ops::Range;
-use web_sys::HtmlCanvasElement;
+use std::str::FromStr;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::{Clamped, JsCast, JsValue};
+#[cfg(feature = "wasm")]
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+/// Squared escape radius. Kept large so the smooth iteration count is
+/// accurate.
+const BAILOUT: f64 = 1e10;
+
+/// Iterations per trip around a palette.
+const PALETTE_PERIOD: f64 = 64.0;
+
+/// Below this pixel size, relative to the magnitude of the view center,
+/// neighbouring pixels become indistinguishable in f64 and double-double
+/// arithmetic is used instead.
+const DEEP_ZOOM: f64 = 1e-12;
+
+/// Rows computed per step of progressive rendering.
+pub const TILE_ROWS: u32 = 16;
 
 /// Draw Mandelbrot set
-pub fn draw(element: HtmlCanvasElement) -> DrawResult<impl Fn((i32, i32)) -> Option<(f64, f64)>> {
-    let backend = CanvasBackend::with_canvas_object(element).unwrap();
+#[cfg(feature = "wasm")]
+pub fn draw(element: HtmlCanvasElement) -> DrawResult<impl CoordMap<f64>> {
+    let backend = CanvasBackend::with_canvas_object(element.clone()).unwrap();
 
     let root = backend.into_drawing_area();
     root.fill(&WHITE)?;
@@ -29,39 +54,473 @@ pub fn draw(element: HtmlCanvasElement) -> DrawResult<impl Fn((i32, i32)) -> Opt
     let (pw, ph) = (range.0.end - range.0.start, range.1.end - range.1.start);
     let (xr, yr) = (chart.x_range(), chart.y_range());
 
-    for (x, y, c) in mandelbrot_set(xr, yr, (pw as usize, ph as usize), 100) {
-        if c != 100 {
-            plotting_area.draw_pixel((x, y), &HSLColor(c as f64 / 100.0, 1.0, 0.5))?;
-        } else {
-            plotting_area.draw_pixel((x, y), &BLACK)?;
-        }
+    let mut buf = vec![0u8; pw as usize * ph as usize * 4];
+    mandelbrot_set(
+        xr,
+        yr,
+        (pw as usize, ph as usize),
+        100,
+        Palette::Rainbow,
+        &mut buf,
+    );
+    root.present()?;
+    put_rgba(&element, &buf, pw as u32, (range.0.start, range.1.start))?;
+
+    Ok(Box::new(chart.into_coord_trans()))
+}
+
+/// Draw an explorer view filling the whole canvas, without axes.
+#[cfg(feature = "wasm")]
+pub fn draw_view(element: HtmlCanvasElement, view: &View) -> DrawResult<()> {
+    draw_rows(element, view, 0..view.size().1)
+}
+
+/// Draw a band of rows of an explorer view, for progressive rendering.
+#[cfg(feature = "wasm")]
+pub fn draw_rows(element: HtmlCanvasElement, view: &View, rows: Range<u32>) -> DrawResult<()> {
+    let rows = rows.start..rows.end.min(view.size().1);
+    if rows.is_empty() {
+        return Ok(());
     }
+    let mut buf = vec![0u8; view.size().0 as usize * rows.len() * 4];
+    view.render_rows(rows.clone(), &mut buf);
+    put_rgba(&element, &buf, view.size().0, (0, rows.start as i32))
+}
 
-    root.present()?;
-    return Ok(Box::new(chart.into_coord_trans()));
+/// Write an RGBA buffer of `size` pixels, such as `View::render` returns, to
+/// a PNG file. Used for benchmarking the renderer outside the browser.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_png(path: &std::path::Path, size: (u32, u32), rgba: &[u8]) -> DrawResult<()> {
+    let rgb: Vec<u8> = rgba
+        .chunks_exact(4)
+        .flat_map(|px| px[..3].to_vec())
+        .collect();
+    let mut backend = BitMapBackend::new(path, size);
+    backend.blit_bitmap((0, 0), size, &rgb)?;
+    backend.present()?;
+    Ok(())
 }
 
+/// Copy an RGBA buffer `width` pixels wide onto the canvas in one call, with
+/// its top left corner at `at`.
+#[cfg(feature = "wasm")]
+fn put_rgba(element: &HtmlCanvasElement, buf: &[u8], width: u32, at: (i32, i32)) -> DrawResult<()> {
+    let js_err = |e: JsValue| -> Box<dyn Error> { format!("{:?}", e).into() };
+    let context = element
+        .get_context("2d")
+        .map_err(js_err)?
+        .ok_or("canvas has no 2d context")?
+        .dyn_into::<CanvasRenderingContext2d>()
+        .map_err(|_| "canvas has no 2d context")?;
+    let image = ImageData::new_with_u8_clamped_array(Clamped(buf), width).map_err(js_err)?;
+    context
+        .put_image_data(&image, at.0 as f64, at.1 as f64)
+        .map_err(js_err)?;
+    Ok(())
+}
+
+fn put_color(px: &mut [u8], color: RGBColor) {
+    px.copy_from_slice(&[color.0, color.1, color.2, 255]);
+}
+
+/// Fill `buf` with RGBA pixels of the Mandelbrot set sampled `samples` times
+/// over `real` x `complex`, top row first.
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
 fn mandelbrot_set(
     real: Range<f64>,
     complex: Range<f64>,
     samples: (usize, usize),
     max_iter: usize,
-) -> impl Iterator<Item = (f64, f64, usize)> {
+    palette: Palette,
+    buf: &mut [u8],
+) {
     let step = (
         (real.end - real.start) / samples.0 as f64,
         (complex.end - complex.start) / samples.1 as f64,
     );
-    return (0..(samples.0 * samples.1)).map(move |k| {
+    for (k, px) in buf.chunks_exact_mut(4).enumerate() {
         let c = (
             real.start + step.0 * (k % samples.0) as f64,
-            complex.start + step.1 * (k / samples.0) as f64,
+            complex.end - step.1 * (k / samples.0) as f64,
         );
-        let mut z = (0.0, 0.0);
-        let mut cnt = 0;
-        while cnt < max_iter && z.0 * z.0 + z.1 * z.1 <= 1e10 {
-            z = (z.0 * z.0 - z.1 * z.1 + c.0, 2.0 * z.0 * z.1 + c.1);
-            cnt += 1;
+        put_color(px, palette.color(escape_f64((0.0, 0.0), c, max_iter)));
+    }
+}
+
+/// Continuous iteration count from the iteration `n` at which |z|^2 passed
+/// the bailout, so colors blend instead of banding.
+fn smooth(n: usize, norm_sqr: f64) -> f64 {
+    n as f64 + 1.0 - (norm_sqr.ln() / 2.0).ln() / std::f64::consts::LN_2
+}
+
+/// Smooth escape time of z -> z^2 + c starting from `z`, or `None` if it
+/// stays bounded for `max_iter` iterations.
+fn escape_f64(mut z: (f64, f64), c: (f64, f64), max_iter: usize) -> Option<f64> {
+    for n in 0..max_iter {
+        let norm_sqr = z.0 * z.0 + z.1 * z.1;
+        if norm_sqr > BAILOUT {
+            return Some(smooth(n, norm_sqr));
+        }
+        z = (z.0 * z.0 - z.1 * z.1 + c.0, 2.0 * z.0 * z.1 + c.1);
+    }
+    None
+}
+
+/// `escape_f64` in double-double arithmetic, for deep zooms.
+fn escape_dd(
+    mut z: (DoubleDouble, DoubleDouble),
+    c: (DoubleDouble, DoubleDouble),
+    max_iter: usize,
+) -> Option<f64> {
+    for n in 0..max_iter {
+        let (re2, im2) = (z.0.sqr(), z.1.sqr());
+        let norm_sqr = (re2 + im2).to_f64();
+        if norm_sqr > BAILOUT {
+            return Some(smooth(n, norm_sqr));
         }
-        return (c.0, c.1, cnt);
-    });
-}
\ No newline at end of file
+        z = (re2 - im2 + c.0, z.0 * z.1 * 2.0 + c.1);
+    }
+    None
+}
+
+/// Which set to render.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fractal {
+    Mandelbrot,
+    /// Julia set for the constant `c = re + im i`.
+    Julia(f64, f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Palette {
+    Rainbow,
+    Fire,
+    Ocean,
+    Grayscale,
+}
+
+impl FromStr for Palette {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Palette, Box<dyn Error>> {
+        match s.to_lowercase().as_str() {
+            "rainbow" => Ok(Palette::Rainbow),
+            "fire" => Ok(Palette::Fire),
+            "ocean" => Ok(Palette::Ocean),
+            "grayscale" | "greyscale" | "gray" | "grey" => Ok(Palette::Grayscale),
+            _ => Err(format!("unknown palette '{}'", s).into()),
+        }
+    }
+}
+
+/// Cyclic linear blend between color stops, `t` in `[0, 1)`.
+fn gradient(stops: &[(u8, u8, u8)], t: f64) -> RGBColor {
+    let pos = t * stops.len() as f64;
+    let i = pos.floor() as usize % stops.len();
+    let (a, b) = (stops[i], stops[(i + 1) % stops.len()]);
+    let f = pos.fract();
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    RGBColor(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+impl Palette {
+    /// Color for a smooth escape time, black for points inside the set.
+    pub fn color(&self, escape: Option<f64>) -> RGBColor {
+        let mu = match escape {
+            Some(mu) => mu.max(0.0),
+            None => return RGBColor(0, 0, 0),
+        };
+        let t = (mu / PALETTE_PERIOD).fract();
+        match self {
+            Palette::Rainbow => {
+                let (r, g, b) = HSLColor(t, 1.0, 0.5).to_backend_color().rgb;
+                RGBColor(r, g, b)
+            }
+            Palette::Fire => gradient(
+                &[(20, 0, 0), (200, 30, 0), (255, 160, 0), (255, 255, 200)],
+                t,
+            ),
+            Palette::Ocean => gradient(
+                &[(0, 7, 100), (32, 107, 203), (237, 255, 255), (0, 170, 200)],
+                t,
+            ),
+            Palette::Grayscale => gradient(&[(30, 30, 30), (240, 240, 240)], t),
+        }
+    }
+}
+
+/// Window onto the complex plane for the zoomable explorer.
+///
+/// The center is kept in double-double precision, so zooming can continue
+/// well past the point where f64 pixel coordinates would collapse together.
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    pub fractal: Fractal,
+    pub palette: Palette,
+    center: (DoubleDouble, DoubleDouble),
+    /// Complex plane units per pixel.
+    pixel_size: f64,
+    /// Pixel size of the unzoomed view, for the zoom factor.
+    home_pixel_size: f64,
+    size: (u32, u32),
+}
+
+impl View {
+    pub fn new(width: u32, height: u32, fractal: Fractal) -> View {
+        let mut view = View {
+            fractal,
+            palette: Palette::Rainbow,
+            center: (0f64.into(), 0f64.into()),
+            pixel_size: 1.0,
+            home_pixel_size: 1.0,
+            size: (width.max(1), height.max(1)),
+        };
+        view.home();
+        view
+    }
+
+    /// Reset to the unzoomed window for the current fractal.
+    pub fn home(&mut self) {
+        let (re, im) = match self.fractal {
+            Fractal::Mandelbrot => (-2.1..0.6, -1.2..1.2),
+            Fractal::Julia(_, _) => (-1.6..1.6, -1.2..1.2),
+        };
+        self.center = (
+            ((re.start + re.end) / 2.0).into(),
+            ((im.start + im.end) / 2.0).into(),
+        );
+        // Fit the whole window, whatever the canvas aspect ratio.
+        self.pixel_size = ((re.end - re.start) / self.size.0 as f64)
+            .max((im.end - im.start) / self.size.1 as f64);
+        self.home_pixel_size = self.pixel_size;
+    }
+
+    pub fn set_fractal(&mut self, fractal: Fractal) {
+        self.fractal = fractal;
+        self.home();
+    }
+
+    /// Change the canvas size, keeping the center and the visible width.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        let ratio = self.size.0 as f64 / width as f64;
+        self.pixel_size *= ratio;
+        self.home_pixel_size *= ratio;
+        self.size = (width, height);
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Magnification relative to the home view.
+    pub fn zoom(&self) -> f64 {
+        self.home_pixel_size / self.pixel_size
+    }
+
+    /// Iteration limit, raised as the zoom deepens so detail near the set's
+    /// boundary keeps resolving.
+    pub fn max_iter(&self) -> usize {
+        let extra = 64.0 * self.zoom().log2().max(0.0);
+        (100.0 + extra).min(20_000.0) as usize
+    }
+
+    /// Whether the view is zoomed past f64 precision.
+    pub fn is_deep(&self) -> bool {
+        let magnitude = self.center.0.hi.abs().max(self.center.1.hi.abs()).max(1.0);
+        self.pixel_size < magnitude * DEEP_ZOOM
+    }
+
+    /// Offset of pixel `(x, y)` from the view center, in plane units.
+    fn offset(&self, x: i32, y: i32) -> (f64, f64) {
+        let dx = x as f64 - self.size.0 as f64 / 2.0;
+        let dy = self.size.1 as f64 / 2.0 - y as f64;
+        (dx * self.pixel_size, dy * self.pixel_size)
+    }
+
+    fn pixel_point(&self, x: i32, y: i32) -> (DoubleDouble, DoubleDouble) {
+        let (dx, dy) = self.offset(x, y);
+        (self.center.0 + dx, self.center.1 + dy)
+    }
+
+    /// Complex plane coordinate of a canvas pixel, like `Chart::coord`.
+    pub fn coord(&self, x: i32, y: i32) -> (f64, f64) {
+        let (re, im) = self.pixel_point(x, y);
+        (re.to_f64(), im.to_f64())
+    }
+
+    /// Center the view on `re + im i`, keeping the zoom.
+    pub fn center_on(&mut self, re: f64, im: f64) {
+        self.center = (re.into(), im.into());
+    }
+
+    /// Move the view by a mouse drag of `(dx, dy)` pixels.
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        self.center = (
+            self.center.0 + (-dx as f64 * self.pixel_size),
+            self.center.1 + (dy as f64 * self.pixel_size),
+        );
+    }
+
+    /// Zoom in by `factor` (below 1 zooms out), keeping the point under
+    /// pixel `(x, y)` fixed.
+    pub fn zoom_at(&mut self, x: i32, y: i32, factor: f64) {
+        if factor <= 0.0 || !factor.is_finite() {
+            return;
+        }
+        let (dx, dy) = self.offset(x, y);
+        let shift = 1.0 - 1.0 / factor;
+        self.center = (self.center.0 + dx * shift, self.center.1 + dy * shift);
+        self.pixel_size /= factor;
+    }
+
+    /// Smooth escape time at a pixel, `None` inside the set.
+    pub fn escape_time(&self, x: u32, y: u32) -> Option<f64> {
+        let p = self.pixel_point(x as i32, y as i32);
+        let max_iter = self.max_iter();
+        match (self.fractal, self.is_deep()) {
+            (Fractal::Mandelbrot, false) => {
+                escape_f64((0.0, 0.0), (p.0.to_f64(), p.1.to_f64()), max_iter)
+            }
+            (Fractal::Julia(re, im), false) => {
+                escape_f64((p.0.to_f64(), p.1.to_f64()), (re, im), max_iter)
+            }
+            (Fractal::Mandelbrot, true) => escape_dd((0f64.into(), 0f64.into()), p, max_iter),
+            (Fractal::Julia(re, im), true) => escape_dd(p, (re.into(), im.into()), max_iter),
+        }
+    }
+
+    pub fn color_at(&self, x: u32, y: u32) -> RGBColor {
+        self.palette.color(self.escape_time(x, y))
+    }
+
+    /// Fill `buf` with the RGBA pixels of `rows`, which must fit the view.
+    pub fn render_rows(&self, rows: Range<u32>, buf: &mut [u8]) {
+        let width = self.size.0 as usize;
+        for (i, px) in buf.chunks_exact_mut(4).take(width * rows.len()).enumerate() {
+            let (x, y) = ((i % width) as u32, rows.start + (i / width) as u32);
+            put_color(px, self.color_at(x, y));
+        }
+    }
+
+    /// RGBA pixels of the whole view.
+    pub fn render(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; self.size.0 as usize * self.size.1 as usize * 4];
+        self.render_rows(0..self.size.1, &mut buf);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mandelbrot::*;
+
+    #[test]
+    fn palettes() {
+        assert_eq!("Fire".parse::<Palette>().unwrap(), Palette::Fire);
+        assert_eq!("grey".parse::<Palette>().unwrap(), Palette::Grayscale);
+        assert!("plaid".parse::<Palette>().is_err());
+        for p in [
+            Palette::Rainbow,
+            Palette::Fire,
+            Palette::Ocean,
+            Palette::Grayscale,
+        ] {
+            assert_eq!(p.color(None), RGBColor(0, 0, 0));
+            // Smooth coloring: nearby escape times give nearby colors.
+            let (a, b) = (p.color(Some(10.0)), p.color(Some(10.05)));
+            assert!((a.0 as i32 - b.0 as i32).abs() <= 8);
+            assert!((a.1 as i32 - b.1 as i32).abs() <= 8);
+            assert!((a.2 as i32 - b.2 as i32).abs() <= 8);
+        }
+    }
+
+    #[test]
+    fn home_view_fits_window() {
+        let v = View::new(270, 240, Fractal::Mandelbrot);
+        let (re, im) = v.coord(0, 0);
+        assert!((re + 2.1).abs() < 1e-9 && (im - 1.2).abs() < 1e-9);
+        let (re, im) = v.coord(270, 240);
+        assert!((re - 0.6).abs() < 1e-9 && (im + 1.2).abs() < 1e-9);
+        assert_eq!(v.zoom(), 1.0);
+        assert_eq!(v.max_iter(), 100);
+    }
+
+    #[test]
+    fn zoom_keeps_cursor_point() {
+        let mut v = View::new(400, 300, Fractal::Mandelbrot);
+        let before = v.coord(123, 45);
+        v.zoom_at(123, 45, 8.0);
+        let after = v.coord(123, 45);
+        assert!((before.0 - after.0).abs() < 1e-12 && (before.1 - after.1).abs() < 1e-12);
+        assert_eq!(v.zoom(), 8.0);
+        assert!(v.max_iter() > 100);
+
+        v.zoom_at(10, 10, 0.125);
+        assert_eq!(v.zoom(), 1.0);
+    }
+
+    #[test]
+    fn pan_follows_drag() {
+        let mut v = View::new(400, 300, Fractal::Mandelbrot);
+        let grabbed = v.coord(100, 100);
+        v.pan(50, -20);
+        let now = v.coord(150, 80);
+        assert!((grabbed.0 - now.0).abs() < 1e-12 && (grabbed.1 - now.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn julia_mode() {
+        // c = 0 gives the unit disk.
+        let mut v = View::new(320, 240, Fractal::Julia(0.0, 0.0));
+        assert_eq!(v.coord(160, 120), (0.0, 0.0));
+        assert_eq!(v.escape_time(160, 120), None);
+        assert!(v.escape_time(0, 0).is_some());
+
+        v.set_fractal(Fractal::Mandelbrot);
+        assert_eq!(v.coord(160, 120), (-0.75, 0.0));
+        assert_eq!(v.escape_time(160, 120), None);
+        assert!(v.escape_time(0, 0).is_some());
+    }
+
+    #[test]
+    fn rgba_buffers() {
+        let v = View::new(40, 30, Fractal::Mandelbrot);
+        let all = v.render();
+        assert_eq!(all.len(), 40 * 30 * 4);
+        // Tiles line up with the full render and with per-pixel colors.
+        let mut tile = vec![0u8; 40 * 7 * 4];
+        v.render_rows(10..17, &mut tile);
+        assert_eq!(tile[..], all[40 * 10 * 4..40 * 17 * 4]);
+        let RGBColor(r, g, b) = v.color_at(3, 12);
+        let i = (2 * 40 + 3) * 4;
+        assert_eq!(tile[i..i + 4], [r, g, b, 255]);
+
+        // Top row first: only the lower sample is outside the set.
+        let mut buf = vec![0u8; 2 * 4];
+        mandelbrot_set(0.0..1.0, -3.0..0.2, (1, 2), 100, Palette::Fire, &mut buf);
+        assert_eq!(buf[..4], [0, 0, 0, 255]);
+        assert_ne!(buf[4..7], [0, 0, 0]);
+    }
+
+    #[test]
+    fn deep_zoom_uses_double_double() {
+        let mut v = View::new(64, 64, Fractal::Mandelbrot);
+        // Zoom into the seahorse valley until pixels are 1e-20 apart.
+        v.center = ((-0.743643887037151).into(), 0.131825904205330.into());
+        let steps = (v.pixel_size / 1e-20).log2().ceil() as usize;
+        for _ in 0..steps {
+            v.zoom_at(32, 32, 2.0);
+        }
+        assert!(v.is_deep());
+
+        // In f64 the neighbouring pixels collapse onto the same point.
+        assert_eq!(v.coord(10, 32), v.coord(11, 32));
+        assert_ne!(v.pixel_point(10, 32), v.pixel_point(11, 32));
+        let distinct: std::collections::HashSet<u64> = (0..64)
+            .filter_map(|x| v.escape_time(x, 10))
+            .map(|mu| mu.to_bits())
+            .collect();
+        assert!(distinct.len() > 1, "deep zoom should not pixelate");
+    }
+}
@@ -1,19 +1,240 @@
+use crate::expr::Expr;
 use crate::DrawResult;
+use plotters::coord::Shift;
 use plotters::prelude::*;
+#[cfg(feature = "wasm")]
 use plotters_canvas::CanvasBackend;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::ops::Range;
+#[cfg(feature = "wasm")]
 use web_sys::HtmlCanvasElement;
 
-pub fn draw(canvas: HtmlCanvasElement, pitch: f64, yaw: f64) -> DrawResult<()> {
+/// Samples along each axis when a surface is given as an expression.
+pub const STEPS: usize = 60;
+
+#[derive(Deserialize, Debug)]
+struct CsvGridPoint {
+    x: f64,
+    z: f64,
+    y: Option<f64>,
+    depth: Option<f64>,
+}
+
+impl CsvGridPoint {
+    fn height(&self) -> Result<f64, Box<dyn Error>> {
+        match (self.y, self.depth) {
+            (Some(y), _) => Ok(y),
+            (None, Some(depth)) => Ok(-depth),
+            (None, None) => Err("needs a y or depth value".into()),
+        }
+    }
+}
+
+/// Heights sampled on a rectangular x/z grid. Cells can be missing, e.g.
+/// land in a bathymetry survey or points where an expression is undefined.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    xs: Vec<f64>,
+    zs: Vec<f64>,
+    /// `ys[k][i]` is the height at `(xs[i], zs[k])`.
+    ys: Vec<Vec<Option<f64>>>,
+}
+
+fn sorted_unique(mut v: Vec<f64>) -> Vec<f64> {
+    v.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    v.dedup();
+    v
+}
+
+fn index_of(v: &[f64], value: f64) -> usize {
+    v.binary_search_by(|p| p.partial_cmp(&value).unwrap_or(Ordering::Equal))
+        .expect("value taken from the same axis")
+}
+
+impl Grid {
+    /// Parse a CSV with one row per sample and an `x,z,y` or `x,z,depth`
+    /// header. `depth` is positive downwards, as in a bathymetry survey,
+    /// and plotted as `y = -depth`. The samples need not be in order and
+    /// gaps in the grid are left undrawn.
+    pub fn from_csv(raw: &str) -> Result<Grid, Box<dyn Error>> {
+        let mut points = Vec::new();
+        for (i, row) in csv::Reader::from_reader(raw.as_bytes())
+            .deserialize()
+            .enumerate()
+        {
+            let row: CsvGridPoint = row.map_err(|e| format!("grid row {}: {}", i + 1, e))?;
+            let y = row
+                .height()
+                .map_err(|e| format!("grid row {}: {}", i + 1, e))?;
+            if !(row.x.is_finite() && row.z.is_finite() && y.is_finite()) {
+                return Err(format!("grid row {}: values must be finite", i + 1).into());
+            }
+            points.push((row.x, row.z, y));
+        }
+
+        let xs = sorted_unique(points.iter().map(|p| p.0).collect());
+        let zs = sorted_unique(points.iter().map(|p| p.1).collect());
+        if xs.len() < 2 || zs.len() < 2 {
+            return Err("grid needs at least two distinct x and z values".into());
+        }
+        let mut ys = vec![vec![None; xs.len()]; zs.len()];
+        for (x, z, y) in points {
+            let cell = &mut ys[index_of(&zs, z)][index_of(&xs, x)];
+            if cell.is_some() {
+                return Err(format!("grid has more than one value at x={} z={}", x, z).into());
+            }
+            *cell = Some(y);
+        }
+        Ok(Grid { xs, zs, ys })
+    }
+
+    /// Sample `y = f(x, z)` at `STEPS` points along each axis.
+    pub fn from_expr(
+        expr: &Expr,
+        x_range: Range<f64>,
+        z_range: Range<f64>,
+    ) -> Result<Grid, Box<dyn Error>> {
+        if let Some(v) = expr
+            .variables()
+            .into_iter()
+            .find(|v| *v != "x" && *v != "z")
+        {
+            return Err(format!("unknown variable '{}', surfaces may only use x and z", v).into());
+        }
+        if !(x_range.start < x_range.end && z_range.start < z_range.end) {
+            return Err("surface ranges must not be empty".into());
+        }
+        let axis = |r: &Range<f64>| -> Vec<f64> {
+            (0..=STEPS)
+                .map(|i| r.start + (r.end - r.start) * i as f64 / STEPS as f64)
+                .collect()
+        };
+        let (xs, zs) = (axis(&x_range), axis(&z_range));
+        let mut first_error = None;
+        let ys = zs
+            .iter()
+            .map(|&z| {
+                xs.iter()
+                    .map(|&x| match expr.eval(&[("x", x), ("z", z)]) {
+                        Ok(y) => Some(y),
+                        Err(e) => {
+                            first_error.get_or_insert(e);
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let grid = Grid { xs, zs, ys };
+        match (grid.y_range(), first_error) {
+            (None, Some(e)) => Err(format!("surface is undefined everywhere: {}", e).into()),
+            _ => Ok(grid),
+        }
+    }
+
+    pub fn x_range(&self) -> Range<f64> {
+        self.xs[0]..self.xs[self.xs.len() - 1]
+    }
+
+    pub fn z_range(&self) -> Range<f64> {
+        self.zs[0]..self.zs[self.zs.len() - 1]
+    }
+
+    /// Lowest and highest sample, `None` if every cell is missing.
+    pub fn y_range(&self) -> Option<Range<f64>> {
+        let mut ys = self.ys.iter().flatten().flatten();
+        let first = *ys.next()?;
+        Some(ys.fold(first..first, |r, &y| r.start.min(y)..r.end.max(y)))
+    }
+
+    /// Keep only the samples inside `x_range` and `z_range`.
+    pub fn crop(&self, x_range: Range<f64>, z_range: Range<f64>) -> Result<Grid, Box<dyn Error>> {
+        let keep = |v: &[f64], r: &Range<f64>| -> Vec<usize> {
+            (0..v.len())
+                .filter(|&i| v[i] >= r.start && v[i] <= r.end)
+                .collect()
+        };
+        let (xi, zi) = (keep(&self.xs, &x_range), keep(&self.zs, &z_range));
+        if xi.len() < 2 || zi.len() < 2 {
+            return Err("crop leaves less than one grid cell".into());
+        }
+        Ok(Grid {
+            xs: xi.iter().map(|&i| self.xs[i]).collect(),
+            zs: zi.iter().map(|&k| self.zs[k]).collect(),
+            ys: zi
+                .iter()
+                .map(|&k| xi.iter().map(|&i| self.ys[k][i]).collect())
+                .collect(),
+        })
+    }
+
+    /// Corners of every grid cell with all four heights present, along with
+    /// their mean height.
+    fn cells(&self) -> impl Iterator<Item = ([(f64, f64, f64); 4], f64)> + '_ {
+        (0..self.zs.len() - 1).flat_map(move |k| {
+            (0..self.xs.len() - 1).filter_map(move |i| {
+                let corner = |i: usize, k: usize| Some((self.xs[i], self.ys[k][i]?, self.zs[k]));
+                let quad = [
+                    corner(i, k)?,
+                    corner(i + 1, k)?,
+                    corner(i + 1, k + 1)?,
+                    corner(i, k + 1)?,
+                ];
+                let mean = quad.iter().map(|c| c.1).sum::<f64>() / 4.0;
+                Some((quad, mean))
+            })
+        })
+    }
+}
+
+/// Color for height `y` within `range`, from deep blue at the bottom
+/// through green to red at the top.
+fn height_color(y: f64, range: &Range<f64>) -> RGBAColor {
+    let t = ((y - range.start) / (range.end - range.start)).clamp(0.0, 1.0);
+    HSLColor(0.66 * (1.0 - t), 0.8, 0.5).mix(0.9)
+}
+
+/// Draw a surface on a canvas element viewed from `pitch` and `yaw`, in
+/// radians.
+#[cfg(feature = "wasm")]
+pub fn draw(
+    canvas: HtmlCanvasElement,
+    grid: &Grid,
+    color_by_height: bool,
+    pitch: f64,
+    yaw: f64,
+) -> DrawResult<()> {
     let area = CanvasBackend::with_canvas_object(canvas)
         .unwrap()
         .into_drawing_area();
-    area.fill(&WHITE)?;
+    draw_on(area, grid, color_by_height, pitch, yaw)
+}
 
-    let x_axis = (-3.0..3.0).step(0.1);
-    let z_axis = (-3.0..3.0).step(0.1);
+/// `draw` onto any plotters drawing area.
+pub fn draw_on<DB>(
+    area: DrawingArea<DB, Shift>,
+    grid: &Grid,
+    color_by_height: bool,
+    pitch: f64,
+    yaw: f64,
+) -> DrawResult<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let y_range = grid.y_range().ok_or("surface has no points to draw")?;
+    let y_axis = if y_range.end - y_range.start < 1e-9 {
+        y_range.start - 1.0..y_range.end + 1.0
+    } else {
+        y_range.clone()
+    };
+
+    area.fill(&WHITE)?;
 
     let mut chart =
-        ChartBuilder::on(&area).build_cartesian_3d(x_axis.clone(), -3.0..3.0, z_axis.clone())?;
+        ChartBuilder::on(&area).build_cartesian_3d(grid.x_range(), y_axis, grid.z_range())?;
 
     chart.with_projection(|mut pb| {
         pb.yaw = yaw;
@@ -24,19 +245,95 @@ pub fn draw(canvas: HtmlCanvasElement, pitch: f64, yaw: f64) -> DrawResult<()> {
 
     chart.configure_axes().draw()?;
 
-    chart.draw_series(
-        SurfaceSeries::xoz(x_axis.values(), z_axis.values(), |x:f64, z:f64| {
-            (x * x + z * z).cos()
-        })
-        .style(&BLUE.mix(0.2)),
-    )?;
-
-    chart.draw_series(LineSeries::new(
-        (-100..100)
-            .map(|y| y as f64 / 40.0)
-            .map(|y| ((y * 10.0).sin(), y, (y * 10.0).cos())),
-        &BLACK,
-    ))?;
+    chart.draw_series(grid.cells().map(|(quad, mean)| {
+        let style = if color_by_height {
+            height_color(mean, &y_range).filled()
+        } else {
+            BLUE.mix(0.2).filled()
+        };
+        Polygon::new(quad.to_vec(), style)
+    }))?;
+    chart.draw_series(grid.cells().map(|(quad, _)| {
+        let mut outline = quad.to_vec();
+        outline.push(quad[0]);
+        PathElement::new(outline, BLUE.mix(0.3))
+    }))?;
 
+    area.present()?;
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plot3d::*;
+
+    const BATHYMETRY: &str = "x,z,depth
+0,0,5
+10,0,12
+0,10,7
+10,10,20
+20,0,15
+";
+
+    #[test]
+    fn csv_grid() {
+        let g = Grid::from_csv(BATHYMETRY).unwrap();
+        assert_eq!(g.xs, vec![0.0, 10.0, 20.0]);
+        assert_eq!(g.zs, vec![0.0, 10.0]);
+        assert_eq!(g.x_range(), 0.0..20.0);
+        assert_eq!(g.y_range(), Some(-20.0..-5.0));
+        // No sample at (20, 10) leaves the second cell undrawn.
+        assert!(g.ys[1][2].is_none());
+        assert_eq!(g.cells().count(), 1);
+        let (quad, mean) = g.cells().next().unwrap();
+        assert_eq!(quad[0], (0.0, -5.0, 0.0));
+        assert_eq!(mean, -11.0);
+    }
+
+    #[test]
+    fn csv_grid_errors() {
+        let err = |raw: &str| Grid::from_csv(raw).unwrap_err().to_string();
+        assert_eq!(err("x,z,y\n0,0,\n"), "grid row 1: needs a y or depth value");
+        assert!(err("x,z,y\n0,0,deep\n").starts_with("grid row 1: "));
+        assert_eq!(
+            err("x,z,y\n0,0,1\n1,0,1\n0,1,1\n0,0,2\n"),
+            "grid has more than one value at x=0 z=0"
+        );
+        assert_eq!(
+            err("x,z,y\n0,0,1\n0,1,1\n"),
+            "grid needs at least two distinct x and z values"
+        );
+    }
+
+    #[test]
+    fn expression_grid() {
+        let e: Expr = "cos(x^2 + z^2)".parse().unwrap();
+        let g = Grid::from_expr(&e, -3.0..3.0, -3.0..3.0).unwrap();
+        assert_eq!(g.cells().count(), STEPS * STEPS);
+        let y = g.y_range().unwrap();
+        assert!(y.start >= -1.0 && y.end <= 1.0);
+
+        // Undefined points drop out, undefined everywhere is an error.
+        let e: Expr = "sqrt(x)".parse().unwrap();
+        let g = Grid::from_expr(&e, -1.0..1.0, 0.0..1.0).unwrap();
+        assert_eq!(g.cells().count(), STEPS / 2 * STEPS);
+        assert!(Grid::from_expr(&e, -2.0..-1.0, 0.0..1.0).is_err());
+
+        let e: Expr = "x * t".parse().unwrap();
+        assert!(Grid::from_expr(&e, 0.0..1.0, 0.0..1.0).is_err());
+    }
+
+    #[test]
+    fn crop_and_draw() {
+        let e: Expr = "x * z".parse().unwrap();
+        let g = Grid::from_expr(&e, 0.0..6.0, 0.0..6.0).unwrap();
+        let c = g.crop(1.0..2.0, 0.0..6.0).unwrap();
+        assert_eq!(c.x_range(), 1.0..2.0);
+        assert_eq!(c.cells().count(), 10 * STEPS);
+        assert!(g.crop(7.0..8.0, 0.0..6.0).is_err());
+
+        let mut buf = vec![0u8; 300 * 300 * 3];
+        let area = BitMapBackend::with_buffer(&mut buf, (300, 300)).into_drawing_area();
+        assert!(draw_on(area, &c, true, 0.5, 0.5).is_ok());
+    }
+}
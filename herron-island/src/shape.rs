@@ -0,0 +1,315 @@
+use crate::DrawResult;
+use plotters::coord::CoordTranslate;
+use plotters::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+const CHARLIE_WELLS: &str = include_str!("../shapes/charlie-wells.json");
+const BUOY: &str = include_str!("../shapes/buoy.svg");
+
+/// A single filled polygon of a `Shape`.
+///
+/// Points are in shape units relative to the shape's anchor, with y pointing
+/// up. The anchor is the point passed to `Shape::draw`, e.g. the waterline
+/// under a vessel.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Part {
+    pub color: (u8, u8, u8),
+    #[serde(default = "opaque")]
+    pub opacity: f64,
+    pub points: Vec<(f32, f32)>,
+}
+
+fn opaque() -> f64 {
+    1.0
+}
+
+/// A marker (vessel, buoy, ...) described as data: an ordered list of
+/// polygons painted back to front.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Shape {
+    pub name: String,
+    pub parts: Vec<Part>,
+}
+
+impl Shape {
+    /// The Charlie Wells, the Herron Island ferry.
+    pub fn charlie_wells() -> Shape {
+        Shape::from_json(CHARLIE_WELLS).expect("built-in Charlie Wells shape")
+    }
+
+    /// A channel buoy.
+    pub fn buoy() -> Shape {
+        Shape::from_svg("Buoy", BUOY).expect("built-in buoy shape")
+    }
+
+    /// Load a shape from either its JSON form or an SVG document, sniffing
+    /// the format from the first non-whitespace character.
+    pub fn parse(raw: &str) -> Result<Shape, Box<dyn Error>> {
+        if raw.trim_start().starts_with('<') {
+            Shape::from_svg("svg", raw)
+        } else {
+            Shape::from_json(raw)
+        }
+    }
+
+    pub fn from_json(raw: &str) -> Result<Shape, Box<dyn Error>> {
+        let shape: Shape = serde_json::from_str(raw)?;
+        if let Some(p) = shape.parts.iter().find(|p| p.points.len() < 3) {
+            return Err(format!(
+                "shape '{}' has a part with {} points, need at least 3",
+                shape.name,
+                p.points.len()
+            )
+            .into());
+        }
+        Ok(shape)
+    }
+
+    /// Load a shape from the `<path>` elements of an SVG document.
+    ///
+    /// Only straight-line path commands are understood (`M`, `L`, `H`, `V`,
+    /// `Z` and their relative forms) along with `fill` as `#rgb`/`#rrggbb`
+    /// and `fill-opacity`. SVG's y axis points down, so it is flipped to
+    /// match chart coordinates.
+    pub fn from_svg(name: &str, raw: &str) -> Result<Shape, Box<dyn Error>> {
+        let mut parts = Vec::new();
+        let mut rest = raw;
+        while let Some(start) = rest.find("<path") {
+            let end = rest[start..]
+                .find('>')
+                .ok_or("unterminated <path> element")?;
+            let tag = &rest[start..start + end];
+            rest = &rest[start + end..];
+
+            let fill = attr(tag, "fill").unwrap_or("#000000");
+            if fill == "none" {
+                continue;
+            }
+            let color = parse_hex_color(fill)?;
+            let opacity = match attr(tag, "fill-opacity") {
+                Some(o) => o.parse::<f64>()?,
+                None => 1.0,
+            };
+            let d = attr(tag, "d").ok_or("<path> element without a 'd' attribute")?;
+            for points in parse_path(d)? {
+                if points.len() >= 3 {
+                    parts.push(Part {
+                        color,
+                        opacity,
+                        points: points.into_iter().map(|(x, y)| (x, -y)).collect(),
+                    });
+                }
+            }
+        }
+
+        if parts.is_empty() {
+            return Err("no filled <path> elements found in SVG".into());
+        }
+        Ok(Shape {
+            name: name.to_string(),
+            parts,
+        })
+    }
+
+    /// Draw the shape with its anchor at chart coordinate `at`, multiplying
+    /// shape units by `scale`.
+    pub fn draw<DB, CT>(
+        &self,
+        chart: &mut ChartContext<DB, CT>,
+        at: (f32, f32),
+        scale: f32,
+    ) -> DrawResult<()>
+    where
+        DB: DrawingBackend,
+        DB::ErrorType: 'static,
+        CT: CoordTranslate<From = (f32, f32)>,
+    {
+        chart.draw_series(self.parts.iter().map(|p| {
+            let points = p
+                .points
+                .iter()
+                .map(|(x, y)| (at.0 + x * scale, at.1 + y * scale))
+                .collect::<Vec<_>>();
+            let (r, g, b) = p.color;
+            Polygon::new(points, RGBColor(r, g, b).mix(p.opacity).filled())
+        }))?;
+        Ok(())
+    }
+}
+
+/// Value of attribute `name` in an element's start tag.
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!("{}=\"", name);
+    let mut search = tag;
+    while let Some(i) = search.find(&key) {
+        // Make sure we matched `fill=` and not the tail of `data-fill=`.
+        let preceded_by_space = search[..i].chars().last().is_some_and(char::is_whitespace);
+        let value = &search[i + key.len()..];
+        if preceded_by_space {
+            return value.find('"').map(|end| &value[..end]);
+        }
+        search = value;
+    }
+    None
+}
+
+fn parse_hex_color(s: &str) -> Result<(u8, u8, u8), Box<dyn Error>> {
+    let hex = s
+        .strip_prefix('#')
+        .filter(|hex| hex.is_ascii())
+        .ok_or_else(|| format!("unsupported fill color '{}'", s))?;
+    let channel = |h: &str| u8::from_str_radix(h, 16);
+    match hex.len() {
+        3 => {
+            let (r, g, b) = (
+                channel(&hex[0..1])?,
+                channel(&hex[1..2])?,
+                channel(&hex[2..3])?,
+            );
+            Ok((r * 17, g * 17, b * 17))
+        }
+        6 => Ok((
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        _ => Err(format!("unsupported fill color '{}'", s).into()),
+    }
+}
+
+enum PathToken {
+    Command(char),
+    Number(f32),
+}
+
+fn tokenize_path(d: &str) -> Result<Vec<PathToken>, Box<dyn Error>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = d.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() || c == ',' {
+            i += 1;
+        } else if c.is_ascii_alphabetic() {
+            tokens.push(PathToken::Command(c));
+            i += 1;
+        } else {
+            let start = i;
+            i += 1;
+            while i < chars.len()
+                && (chars[i].is_ascii_digit() || chars[i] == '.' && !chars[start..i].contains(&'.'))
+            {
+                i += 1;
+            }
+            let n: String = chars[start..i].iter().collect();
+            let n = n
+                .parse::<f32>()
+                .map_err(|_| format!("invalid number '{}' in SVG path", n))?;
+            tokens.push(PathToken::Number(n));
+        }
+    }
+    Ok(tokens)
+}
+
+type Subpath = Vec<(f32, f32)>;
+
+/// Split a path's `d` attribute into its subpaths' absolute points.
+fn parse_path(d: &str) -> Result<Vec<Subpath>, Box<dyn Error>> {
+    let tokens = tokenize_path(d)?;
+    let mut subpaths = Vec::new();
+    let mut current: Subpath = Vec::new();
+    let mut pos = (0f32, 0f32);
+    let mut command = None;
+
+    let mut i = 0;
+    while i < tokens.len() {
+        if let PathToken::Command(c) = tokens[i] {
+            i += 1;
+            if c == 'Z' || c == 'z' {
+                if let Some(first) = current.first() {
+                    pos = *first;
+                }
+                subpaths.push(std::mem::take(&mut current));
+                command = None;
+                continue;
+            }
+            command = Some(c);
+        }
+
+        let c = command.ok_or("SVG path data must start with a command")?;
+        let mut number = || match tokens.get(i) {
+            Some(PathToken::Number(n)) => {
+                i += 1;
+                Ok(*n)
+            }
+            _ => Err(format!("missing coordinate for '{}' in SVG path", c)),
+        };
+        let relative = c.is_ascii_lowercase();
+        let origin = if relative { pos } else { (0.0, 0.0) };
+        pos = match c.to_ascii_uppercase() {
+            'M' | 'L' => (origin.0 + number()?, origin.1 + number()?),
+            'H' => (origin.0 + number()?, pos.1),
+            'V' => (pos.0, origin.1 + number()?),
+            _ => return Err(format!("unsupported SVG path command '{}'", c).into()),
+        };
+
+        if c.eq_ignore_ascii_case(&'M') {
+            if !current.is_empty() {
+                subpaths.push(std::mem::take(&mut current));
+            }
+            // Coordinates following a moveto are implicit linetos.
+            command = Some(if relative { 'l' } else { 'L' });
+        }
+        current.push(pos);
+    }
+
+    if !current.is_empty() {
+        subpaths.push(current);
+    }
+    Ok(subpaths)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shape::*;
+
+    #[test]
+    fn builtin_shapes_load() {
+        assert_eq!(Shape::charlie_wells().parts.len(), 11);
+        assert_eq!(Shape::buoy().parts.len(), 3);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let cw = Shape::charlie_wells();
+        let raw = serde_json::to_string(&cw).unwrap();
+        assert_eq!(Shape::parse(&raw).unwrap(), cw);
+    }
+
+    #[test]
+    fn svg_path_subset() {
+        let svg = r##"<svg><path fill="#f00" fill-opacity="0.5" d="M0,0 h2 v-1 l-2,0z m0 -2 1-1 1,1"/></svg>"##;
+        let s = Shape::parse(svg).unwrap();
+        assert_eq!(s.parts.len(), 2);
+        assert_eq!(s.parts[0].color, (255, 0, 0));
+        assert_eq!(s.parts[0].opacity, 0.5);
+        assert_eq!(
+            s.parts[0].points,
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)]
+        );
+        assert_eq!(s.parts[1].points, vec![(0.0, 2.0), (1.0, 3.0), (2.0, 2.0)]);
+    }
+
+    #[test]
+    fn svg_errors() {
+        assert!(Shape::parse(r#"<svg><path d="M0 0 C1 1 2 2 3 3"/></svg>"#).is_err());
+        assert!(Shape::parse(r#"<svg><path fill="red" d="M0 0 L1 0 L1 1"/></svg>"#).is_err());
+        assert!(Shape::parse(r##"<svg><path fill="#é1" d="M0 0 L1 0 L1 1"/></svg>"##).is_err());
+        assert!(Shape::parse("<svg></svg>").is_err());
+        assert!(Shape::parse(
+            r#"{"name": "dot", "parts": [{"color": [0,0,0], "points": [[0,0]]}]}"#
+        )
+        .is_err());
+    }
+}
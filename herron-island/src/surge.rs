@@ -0,0 +1,181 @@
+use crate::tides::{parse_noaa_time, TidePoint};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::error::Error;
+
+/// Standard sea level pressure, in hPa.
+pub const STANDARD_PRESSURE: f32 = 1013.25;
+
+/// Inverse barometer response: sea level rises about 1 cm for every hPa the
+/// pressure drops below the reference.
+const FEET_PER_HPA: f32 = 0.0328;
+
+#[derive(Deserialize, Debug)]
+struct CsvWeather {
+    t: String,
+    surge: f32,
+    pressure: Option<f32>,
+}
+
+impl CsvWeather {
+    fn to_weather_point(&self) -> Result<WeatherPoint, Box<dyn Error>> {
+        Ok(WeatherPoint {
+            dt: parse_noaa_time(&self.t)?,
+            surge: self.surge,
+            pressure: self.pressure,
+        })
+    }
+}
+
+/// Observed or forecast conditions at one point in time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeatherPoint {
+    pub dt: DateTime<Utc>,
+    /// Wind setup or other surge offset, in feet.
+    pub surge: f32,
+    /// Barometric pressure, in hPa.
+    pub pressure: Option<f32>,
+}
+
+/// Weather adjustment layered on top of the astronomical tide prediction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weather {
+    points: Vec<WeatherPoint>,
+    reference_pressure: f32,
+}
+
+impl Weather {
+    pub fn new(mut points: Vec<WeatherPoint>) -> Weather {
+        points.sort_by_key(|p| p.dt);
+        Weather {
+            points,
+            reference_pressure: STANDARD_PRESSURE,
+        }
+    }
+
+    /// Parse a CSV with a `t,surge,pressure` header. Times use the same
+    /// `%Y-%m-%d %H:%M` format as NOAA predictions, `surge` is in feet and
+    /// the `pressure` column (hPa) may be left empty.
+    pub fn from_csv(raw: &str) -> Result<Weather, Box<dyn Error>> {
+        let mut points = Vec::new();
+        for (i, row) in csv::Reader::from_reader(raw.as_bytes())
+            .deserialize()
+            .enumerate()
+        {
+            let row: CsvWeather = row?;
+            let point = row
+                .to_weather_point()
+                .map_err(|e| format!("weather row {}: {}", i + 1, e))?;
+            points.push(point);
+        }
+        Ok(Weather::new(points))
+    }
+
+    /// Use a pressure other than `STANDARD_PRESSURE` as the level at which
+    /// the inverse barometer correction is zero.
+    pub fn with_reference_pressure(mut self, hpa: f32) -> Weather {
+        self.reference_pressure = hpa;
+        self
+    }
+
+    /// Total correction to the predicted level at `dt`, in feet.
+    ///
+    /// Surge and pressure are interpolated linearly between samples. Outside
+    /// the range of the series no correction is applied.
+    pub fn offset_at(&self, dt: DateTime<Utc>) -> f32 {
+        let i = self.points.partition_point(|p| p.dt <= dt);
+        let (a, b) = match (i.checked_sub(1).map(|i| self.points[i]), self.points.get(i)) {
+            (Some(a), _) if a.dt == dt => (a, a),
+            (Some(a), Some(b)) => (a, *b),
+            _ => return 0f32,
+        };
+
+        let span = (b.dt - a.dt).num_seconds() as f32;
+        let f = if span > 0f32 {
+            (dt - a.dt).num_seconds() as f32 / span
+        } else {
+            0f32
+        };
+        let lerp = |from: f32, to: f32| from + (to - from) * f;
+
+        let surge = lerp(a.surge, b.surge);
+        let barometer = match (a.pressure, b.pressure) {
+            (Some(pa), Some(pb)) => (self.reference_pressure - lerp(pa, pb)) * FEET_PER_HPA,
+            _ => 0f32,
+        };
+        surge + barometer
+    }
+
+    /// Apply the correction to a series of predicted tide points.
+    pub fn adjust(&self, tv: &[TidePoint]) -> Vec<TidePoint> {
+        tv.iter()
+            .map(|t| TidePoint::new(t.dt(), t.level() + self.offset_at(t.dt()), t.tide()))
+            .collect()
+    }
+
+    /// Apply the correction to chart points whose x is hours since `today`.
+    pub fn adjust_xy(&self, xys: &[(f32, f32)], today: DateTime<Utc>) -> Vec<(f32, f32)> {
+        xys.iter()
+            .map(|&(x, y)| {
+                let dt = today + Duration::seconds((x * 3600f32) as i64);
+                (x, y + self.offset_at(dt))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::surge::*;
+    use crate::tides::Tide;
+
+    const WEATHER: &str = "t,surge,pressure
+2022-01-10 06:00,0.5,993.25
+2022-01-10 00:00,0.0,1013.25
+2022-01-10 12:00,1.0,
+";
+
+    #[test]
+    fn csv_parse_sorts_points() {
+        let w = Weather::from_csv(WEATHER).unwrap();
+        assert_eq!(w.points.len(), 3);
+        assert!(w.points.windows(2).all(|p| p[0].dt < p[1].dt));
+        assert_eq!(w.points[2].pressure, None);
+    }
+
+    #[test]
+    fn csv_errors_name_the_row() {
+        let err = Weather::from_csv("t,surge,pressure\n2022-13-10 06:00,0.5,\n").unwrap_err();
+        assert!(err.to_string().starts_with("weather row 1"));
+        assert!(Weather::from_csv("t,surge\n2022-01-10 06:00,high\n").is_err());
+    }
+
+    #[test]
+    fn offsets() {
+        let w = Weather::from_csv(WEATHER).unwrap();
+        let at = |s| w.offset_at(parse_noaa_time(s).unwrap());
+
+        assert_eq!(at("2022-01-10 00:00"), 0f32);
+        // 20 hPa low adds about 0.66 ft on top of the surge.
+        assert!((at("2022-01-10 06:00") - (0.5 + 20f32 * FEET_PER_HPA)).abs() < 1e-5);
+        assert!((at("2022-01-10 03:00") - (0.25 + 10f32 * FEET_PER_HPA)).abs() < 1e-5);
+        // Missing pressure drops the barometer term but keeps the surge.
+        assert!((at("2022-01-10 09:00") - 0.75).abs() < 1e-5);
+        assert_eq!(at("2022-01-10 12:00"), 1.0);
+        assert_eq!(at("2022-01-09 23:00"), 0f32);
+        assert_eq!(at("2022-01-10 12:01"), 0f32);
+    }
+
+    #[test]
+    fn adjust_tide_points() {
+        let w = Weather::from_csv(WEATHER).unwrap();
+        let t = TidePoint::new(
+            parse_noaa_time("2022-01-10 12:00").unwrap(),
+            14.5,
+            Tide::High,
+        );
+        let adjusted = w.adjust(&[t]);
+        assert_eq!(adjusted[0].level(), 15.5);
+        assert_eq!(adjusted[0].tide(), Tide::High);
+    }
+}
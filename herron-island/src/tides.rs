@@ -1,70 +1,63 @@
-use crate::utils::*;
-use crate::DrawResult;
+use crate::shape::Shape;
+use crate::surge::Weather;
+use crate::{CoordMap, DrawResult};
 use chrono::prelude::*;
 use chrono::{DateTime, Duration, Local, TimeZone, Utc};
-use plotters::element::*;
-use plotters::{self};
-use plotters::{prelude::*, style::RGBAColor};
+use plotters::{self, coord::Shift};
+use plotters::prelude::*;
+#[cfg(feature = "wasm")]
 use plotters_canvas::CanvasBackend;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use wasm_bindgen::prelude::*;
-//use image::{imageops::FilterType, ImageFormat};
-
-use std::fs::File;
-use std::io::BufReader;
-
-extern crate web_sys;
 
 // A macro to provide `println!(..)`-style syntax for `console.log` logging.
+// It is a no-op outside the browser so the tide logic and charts can be used
+// from native code.
 macro_rules! log_wasm {
     ( $( $t:tt )* ) => {
+        #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
         web_sys::console::log_1(&format!( $( $t )* ).into());
     }
 }
 
-#[cfg(target_family = "unix")]
-macro_rules! log {
-    ( $( $t:tt )* ) => {
-        println!( $( $t )* );
-    }
-}
-
-// Call the JS alert() callback.
-#[wasm_bindgen]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-extern "C" {
-    unsafe fn alert(s: &str);
-}
-
-// When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
-// allocator.
-#[cfg(feature = "wee_alloc")]
-#[global_allocator]
-static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-
-/// Draw power function f(x) = x^power.
+/// Draw the tide chart for today on the canvas with id `canvas_id`.
+#[cfg(feature = "wasm")]
 pub fn draw(
     canvas_id: &str,
     tv: Vec<TidePoint>,
-    draw_cw: bool,
-) -> DrawResult<impl Fn((i32, i32)) -> Option<(f32, f32)>> {
+    marker: Option<&Shape>,
+    weather: Option<&Weather>,
+) -> DrawResult<impl CoordMap<f32>> {
     let backend = CanvasBackend::new(canvas_id).expect("cannot find canvas");
-    let root = backend.into_drawing_area();
+    draw_on(
+        backend.into_drawing_area(),
+        Local::now().naive_local(),
+        tv,
+        marker,
+        weather,
+    )
+}
+
+/// Draw the tide chart for the day of `now`, a local wall clock time, on
+/// any plotters backend.
+pub fn draw_on<DB>(
+    root: DrawingArea<DB, Shift>,
+    now: NaiveDateTime,
+    tv: Vec<TidePoint>,
+    marker: Option<&Shape>,
+    weather: Option<&Weather>,
+) -> DrawResult<impl CoordMap<f32>>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
     let label_style = TextStyle::from(("sans-serif", 10).into_font()).color(&WHITE);
     root.fill(&RGBColor(54, 95, 145))?;
 
-    // Find the local date, flatten to naive date, then convert it to a UTC date.
-    let now = Local::now();
-    let l = now
-        .with_hour(0)
-        .unwrap()
-        .with_minute(0)
-        .unwrap()
-        .with_second(0)
-        .unwrap();
-    let neh = l.naive_local();
-    let today = Utc.from_local_datetime(&neh).unwrap();
+    // Flatten the local time to midnight, then treat it as a UTC date like
+    // the prediction timestamps.
+    let neh = now.date().and_hms_opt(0, 0, 0).unwrap();
+    let today = Utc.from_utc_datetime(&neh);
     log_wasm!("Local: {:?} Naive:{:?}", now, neh);
 
     let chart_top = 20f32;
@@ -87,9 +80,14 @@ pub fn draw(
         .draw()?;
 
     let xys = coordinates_from_prediction(tv.to_owned(), today);
+    // The sea is drawn at the weather adjusted level when we have one.
+    let sea = match weather {
+        Some(w) => w.adjust_xy(&xys, today),
+        None => xys.clone(),
+    };
     //log_wasm!("xys read: {:?}", xys.len());
-    chart.draw_series(AreaSeries::new(
-        xys.iter().filter_map(|(x, y)| {
+    let sea_anno = chart.draw_series(AreaSeries::new(
+        sea.iter().filter_map(|(x, y)| {
             if *x > 0f32 && *x < 24f32 {
                 return Some((x.clone(), y.clone()));
             }
@@ -98,9 +96,17 @@ pub fn draw(
         -10.0,
         RGBColor(139, 166, 214).mix(0.5),
     ))?;
+    if weather.is_some() {
+        sea_anno.label("Weather adjusted").legend(|(x, y)| {
+            Rectangle::new(
+                [(x, y - 4), (x + 20, y + 4)],
+                RGBColor(139, 166, 214).filled(),
+            )
+        });
+    }
     // Add some whitecaps to the sea
     chart.draw_series(LineSeries::new(
-        xys.iter().filter_map(|(x, y)| {
+        sea.iter().filter_map(|(x, y)| {
             if *x > 0f32 && *x < 24f32 {
                 return Some((x.clone(), y.clone()));
             }
@@ -109,10 +115,29 @@ pub fn draw(
         RGBColor(206, 222, 248).stroke_width(1),
     ))?;
 
+    // Show the astronomical tide alongside the adjusted sea
+    if weather.is_some() {
+        let astro_style = RGBColor(255, 214, 102).stroke_width(1);
+        chart
+            .draw_series(LineSeries::new(
+                xys.iter().copied().filter(|(x, _)| *x > 0f32 && *x < 24f32),
+                astro_style,
+            ))?
+            .label("Astronomical")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], astro_style));
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .label_font(("sans-serif", 10).into_font().color(&WHITE))
+            .background_style(RGBColor(54, 95, 145).mix(0.8))
+            .border_style(WHITE)
+            .draw()?;
+    }
+
     // Draw vertical line to show current time
     let x_val = now.hour() as f32 + (now.minute() as f32 / 60f32);
     let x_split = 0.05f32;
-    let y_val = xys
+    let y_val = sea
         .iter()
         .find_map(|(x, y)| if x > &x_val { Some(y) } else { None })
         .unwrap();
@@ -168,235 +193,41 @@ pub fn draw(
         },
     ))?;
 
-    // Draw the Charlie Wells
-    /* Seems not possible with Plotters 3.x, may have worked in 2.x
-    let (w, h) = chart.plotting_area().dim_in_pixel();
-    let image = image::load(
-        BufReader::new(
-            File::open("www/charlie-wells.svg").map_err(|e| {
-                eprintln!("Unable to open file charlie-wells.svg");
-                e
-            })?),
-        ImageFormat::Png,
-    )?
-    .resize_exact(w - w / 10, h - h / 10, FilterType::Nearest);
-    */
-
-    // Draw Charlie Wells manually
-    if draw_cw {
-        //let ch_scale = 0.3f32;
-        let ch_scale = 0.5f32;
-        let oy = *y_val+(1.75f32*ch_scale);
-
-        let mut xs: Vec<f32> = Vec::new();
-        xs.push(x_val);
-
-        // Draw cabin(white)
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            let cabin_width = 0.8f32;
-            Rectangle::new(
-                [
-                    (ox - (&cabin_width) * ch_scale, oy - (1.0f32) * ch_scale),
-                    (ox + (&cabin_width) * ch_scale, oy + (1.0f32) * ch_scale),
-                ],
-                RGBColor(255, 255, 255).filled(),
-            )
-        }))?;
-
-        // Draw Deck(Red...)
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            let deck_width = 1.50f32;
-            Polygon::new(
-                [
-                    (ox - (&deck_width * ch_scale), oy - (1.0f32 * ch_scale)),
-                    (ox + (&deck_width * ch_scale), oy - (1.0f32 * ch_scale)),
-                    (ox + (&deck_width - 0.2f32) * ch_scale, oy),
-                    (ox - (&deck_width - 0.2f32) * ch_scale, oy),
-                ],
-                RGBColor(230, 0,0).filled(),
-            )
-        }))?;
-
-        // Draw Hull(dark red)
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            let hull_width = 1.75f32;
-            Polygon::new(
-                [
-                    (ox - (&hull_width) * ch_scale, oy - (1.0f32) * ch_scale),
-                    (
-                        ox - ((&hull_width - 0.25f32) * ch_scale),
-                        oy - (2.0f32 * ch_scale),
-                    ),
-                    (
-                        ox + ((&hull_width - 0.25f32) * ch_scale),
-                        oy - (2.0f32 * ch_scale),
-                    ),
-                    (ox + ((&hull_width) * ch_scale), oy - ((1.0f32) * ch_scale)),
-                ],
-                RGBColor(72, 0, 0).filled(),
-            )
-        }))?;
-
-        // Draw Bridge
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            let bridge_width = 0.25f32;
-            Polygon::new(
-                [
-                    (ox - (&bridge_width * ch_scale), oy + (1.0f32 * ch_scale)),
-                    (ox + (&bridge_width * ch_scale), oy + (1.0f32 * ch_scale)),
-                    (ox + (&bridge_width * ch_scale), oy + (1.5f32 * ch_scale)),
-                    (
-                        ox + ((&bridge_width + 0.14f32) * ch_scale),
-                        oy + (2.5f32 * ch_scale),
-                    ),
-                    (
-                        ox - ((&bridge_width + 0.14f32) * ch_scale),
-                        oy + (2.5f32 * ch_scale),
-                    ),
-                    (ox - (&bridge_width) * ch_scale, oy + (1.5f32 * ch_scale)),
-                ],
-                HSLColor(200.0, 200.0, 200.0).filled(),
-            )
-        }))?;
-
-        // draw smoke stacks
-        let stack_origin_width = 0.5f32; 
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (
-                        ox + (stack_origin_width * ch_scale),
-                        oy+ (1.0f32 * ch_scale),
-                    ),
-                    (
-                        ox + ((stack_origin_width + 0.1f32) * ch_scale),
-                        oy+ (2.5f32 * ch_scale),
-)
-                ],
-                RGBColor(128, 128, 128).filled(),
-            )
-        }))?;
-
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (
-                        ox - (stack_origin_width * ch_scale),
-                        oy+ (1.0f32 * ch_scale),
-                    ),
-                    (
-                        ox - ((stack_origin_width + 0.1f32) * ch_scale),
-                        oy+ (2.5f32 * ch_scale),
-)
-                ],
-                RGBColor(128, 128, 128).filled(),
-            )
-        }))?;
-
-        // Draw flag
-        // -- White background
-        let flag_width = 0.6f32;
-        let flag_floor = 3.0f32;
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (ox, oy + (flag_floor * ch_scale)),
-                    (
-                        ox + (&flag_width * ch_scale),
-                        oy + ((flag_floor + 1.0f32) * ch_scale),
-                    ),
-                ],
-                RGBColor(255, 255, 255).filled(),
-            )
-        }))?;
-
-        // -- red stripes
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (
-                        ox + (&flag_width * ch_scale),
-                        oy + ((flag_floor + 1.0f32) * ch_scale),
-                    ),
-                    (ox, oy + ((flag_floor + 0.8f32) * ch_scale)),
-                ],
-                RGBColor(255, 0, 0).filled(),
-            )
-        }))?;
-
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (
-                        ox + (&flag_width * ch_scale),
-                        oy + ((flag_floor + 0.6f32) * ch_scale),
-                    ),
-                    (ox, oy + ((flag_floor + 0.4f32) * ch_scale)),
-                ],
-                RGBColor(255, 0, 0).filled(),
-            )
-        }))?;
+    // Draw the marker (the Charlie Wells by default) riding the current tide level
+    if let Some(shape) = marker {
+        shape.draw(&mut chart, (x_val, *y_val), 0.5)?;
+    }
 
-        /*
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (
-                        ox + (&flag_width * ch_scale),
-                        oy + ((flag_floor + 0.4f32) * ch_scale),
-                    ),
-                    (ox, oy + ((flag_floor + 0.2f32) * ch_scale)),
-                ],
-                RGBColor(255, 0, 0).filled(),
-            )
-        }))?;
-        */
+    root.present()?;
+    return Ok(chart.into_coord_trans());
+}
 
-        // red stripes
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (
-                        ox + (&flag_width * ch_scale),
-                        oy + ((flag_floor + 0.2f32) * ch_scale),
-                    ),
-                    (ox, oy + ((flag_floor) * ch_scale)),
-                ],
-                RGBColor(255, 0, 0).filled(),
-            )
-        }))?;
+/// The current local wall clock time, in the same naive-as-UTC form the
+/// prediction timestamps are parsed into.
+pub fn station_now() -> DateTime<Utc> {
+    Utc.from_utc_datetime(&Local::now().naive_local())
+}
 
-        // -- blue patches
-        chart.draw_series(xs.iter().map(|x| {
-            let ox: f32 = *x;
-            Rectangle::new(
-                [
-                    (ox, oy + ((flag_floor + 1.0f32) * ch_scale)),
-                    (
-                        ox + (0.3f32 * ch_scale),
-                        oy + ((flag_floor + 0.6f32) * ch_scale),
-                    ),
-                ],
-                RGBColor(0, 0, 255).filled(),
-            )
-        }))?;
+/// Interpolated tide level at `dt`, following the same half cosine between
+/// consecutive predictions that the chart draws. `tv` must be sorted by time;
+/// returns `None` outside the predicted range.
+pub fn level_at(tv: &[TidePoint], dt: DateTime<Utc>) -> Option<f32> {
+    let i = tv.partition_point(|t| t.dt <= dt);
+    let a = tv.get(i.checked_sub(1)?)?;
+    if a.dt == dt {
+        return Some(a.level);
     }
+    let b = tv.get(i)?;
 
-    root.present()?;
-    return Ok(chart.into_coord_trans());
+    let x_percentage = (dt - a.dt).num_seconds() as f32 / (b.dt - a.dt).num_seconds() as f32;
+    let to_cosine = (x_percentage * std::f32::consts::PI) + std::f32::consts::PI;
+    let y_multiplier = (to_cosine.cos() + 1f32) / 2f32;
+    Some((y_multiplier * (b.level - a.level)) + a.level)
 }
 
+/// Number of segments drawn between consecutive predictions.
+const CURVE_STEPS: usize = 25;
+
 fn coordinates_from_prediction(tv: Vec<TidePoint>, today: DateTime<Utc>) -> Vec<(f32, f32)> {
     let chart_start = today - Duration::hours(12);
     let chart_end = chart_start + Duration::days(2);
@@ -417,17 +248,10 @@ fn coordinates_from_prediction(tv: Vec<TidePoint>, today: DateTime<Utc>) -> Vec<
         .collect();
 
     valid_tp.iter().for_each(|t| {
-        let (x, y) = t.to_xy();
-        if t.dt < today {
-            let x_adj = x - 24f32;
-            xs.push(x_adj);
-        } else if t.dt > today + Duration::hours(24) {
-            let x_adj = 24f32 + x;
-            xs.push(x_adj);
-        } else {
-            xs.push(x);
-        }
-        ys.push(y);
+        // Hours since midnight today, so points either side of today carry on
+        // below 0 and past 24 instead of wrapping around.
+        xs.push((t.dt - today).num_minutes() as f32 / 60f32);
+        ys.push(t.level);
     });
 
     // Graphed X,Y vector points
@@ -443,28 +267,30 @@ fn coordinates_from_prediction(tv: Vec<TidePoint>, today: DateTime<Utc>) -> Vec<
         let y_delta = ys[i] - y_origin;
         let x_delta = xs[i] - x_origin;
 
-        let x_inc = x_delta / 25f32;
-
-        let mut x_step = x_origin + x_inc;
-        while x_step < xs[i] {
-            tide_x.push(x_step);
+        for step in 1..CURVE_STEPS {
+            let x_percentage = step as f32 / CURVE_STEPS as f32;
+            tide_x.push(x_origin + (x_percentage * x_delta));
             // calculate Y
             // --------------------------
-            let x_percentage = (x_step - x_origin) / x_delta;
             let to_cosine = (x_percentage * std::f32::consts::PI) + std::f32::consts::PI;
             let y_multiplier = (to_cosine.cos() + 1f32) / 2f32;
             let y_val = (y_multiplier * y_delta) + y_origin;
             tide_y.push(y_val);
-            x_step += x_inc;
         }
     }
-
-    let mut ret = Vec::<(f32, f32)>::new();
-    for (_, (x, y)) in tide_x.iter().zip(tide_y.iter()).enumerate() {
-        ret.push((x.clone(), y.clone()));
+    // Finish the curve on the last prediction
+    if let (Some(x), Some(y)) = (xs.last(), ys.last()) {
+        tide_x.push(*x);
+        tide_y.push(*y);
     }
 
-    ret
+    tide_x.into_iter().zip(tide_y).collect()
+}
+
+/// Parse a timestamp in the `%Y-%m-%d %H:%M` format NOAA predictions use.
+pub(crate) fn parse_noaa_time(t: &str) -> Result<DateTime<Utc>, Box<dyn Error>> {
+    let t = chrono::NaiveDateTime::parse_from_str(t, "%Y-%m-%d %H:%M")?;
+    Ok(Utc.from_utc_datetime(&t))
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -478,7 +304,7 @@ struct JsonTide {
 
 impl JsonTide {
     pub fn to_tide_point(&self) -> Result<TidePoint, Box<dyn Error>> {
-        let t = chrono::NaiveDateTime::parse_from_str(self.t.as_str(), "%Y-%m-%d %H:%M")?;
+        let dt = parse_noaa_time(self.t.as_str())?;
         let h = self.v.parse::<f32>()?;
         let tide = match self.tide.as_str() {
             "H" => Tide::High,
@@ -486,9 +312,6 @@ impl JsonTide {
             _ => Tide::None,
         };
 
-        let n = Utc::now();
-        let dt = DateTime::from_utc(t, *n.offset());
-
         Ok(TidePoint {
             dt: dt,
             level: h,
@@ -541,6 +364,18 @@ impl TidePoint {
         }
     }
 
+    pub fn dt(&self) -> DateTime<Utc> {
+        self.dt
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn tide(&self) -> Tide {
+        self.tide
+    }
+
     pub fn to_string(&self) -> String {
         return format!("{:?}::{}:{}", self.tide, self.dt, self.level);
     }
@@ -561,15 +396,16 @@ mod tests {
     use crate::tides::*;
     use chrono::prelude::*;
     use chrono::{DateTime, TimeZone, Utc};
+    use proptest::prelude::*;
 
-    const predicted_json_data: &str = r#"{ "predictions" : [{"t":"2022-01-10 05:05", "v":"5.086", "type":"L"},{"t":"2022-01-10 11:32", "v":"14.668", "type":"H"},{"t":"2022-01-10 19:03", "v":"2.498", "type":"L"},{"t":"2022-01-11 01:42", "v":"10.228", "type":"H"},{"t":"2022-01-11 06:15", "v":"6.854", "type":"L"},{"t":"2022-01-11 12:11", "v":"14.150", "type":"H"},{"t":"2022-01-11 19:51", "v":"1.503", "type":"L"},{"t":"2022-01-12 03:19", "v":"11.508", "type":"H"},{"t":"2022-01-12 07:45", "v":"8.101", "type":"L"},{"t":"2022-01-12 12:51", "v":"13.639", "type":"H"},{"t":"2022-01-12 20:33", "v":"0.666", "type":"L"}]}"#;
+    const PREDICTED_JSON_DATA: &str = r#"{ "predictions" : [{"t":"2022-01-10 05:05", "v":"5.086", "type":"L"},{"t":"2022-01-10 11:32", "v":"14.668", "type":"H"},{"t":"2022-01-10 19:03", "v":"2.498", "type":"L"},{"t":"2022-01-11 01:42", "v":"10.228", "type":"H"},{"t":"2022-01-11 06:15", "v":"6.854", "type":"L"},{"t":"2022-01-11 12:11", "v":"14.150", "type":"H"},{"t":"2022-01-11 19:51", "v":"1.503", "type":"L"},{"t":"2022-01-12 03:19", "v":"11.508", "type":"H"},{"t":"2022-01-12 07:45", "v":"8.101", "type":"L"},{"t":"2022-01-12 12:51", "v":"13.639", "type":"H"},{"t":"2022-01-12 20:33", "v":"0.666", "type":"L"}]}"#;
 
-    const prediction: &str = r#"{ "predictions" : [{"t":"2022-05-27 03:49", "v":"14.036", "type":"H"},{"t":"2022-05-27 11:02", "v":"-0.058", "type":"L"},{"t":"2022-05-27 17:51", "v":"12.111", "type":"H"},{"t":"2022-05-27 23:02", "v":"5.967", "type":"L"},{"t":"2022-05-28 04:17", "v":"13.733", "type":"H"},{"t":"2022-05-28 11:32", "v":"-0.922", "type":"L"},{"t":"2022-05-28 18:42", "v":"12.991", "type":"H"},{"t":"2022-05-28 23:53", "v":"6.817", "type":"L"},{"t":"2022-05-29 04:44", "v":"13.355", "type":"H"},{"t":"2022-05-29 12:01", "v":"-1.516", "type":"L"},{"t":"2022-05-29 19:25", "v":"13.647", "type":"H"}
+    const PREDICTION: &str = r#"{ "predictions" : [{"t":"2022-05-27 03:49", "v":"14.036", "type":"H"},{"t":"2022-05-27 11:02", "v":"-0.058", "type":"L"},{"t":"2022-05-27 17:51", "v":"12.111", "type":"H"},{"t":"2022-05-27 23:02", "v":"5.967", "type":"L"},{"t":"2022-05-28 04:17", "v":"13.733", "type":"H"},{"t":"2022-05-28 11:32", "v":"-0.922", "type":"L"},{"t":"2022-05-28 18:42", "v":"12.991", "type":"H"},{"t":"2022-05-28 23:53", "v":"6.817", "type":"L"},{"t":"2022-05-29 04:44", "v":"13.355", "type":"H"},{"t":"2022-05-29 12:01", "v":"-1.516", "type":"L"},{"t":"2022-05-29 19:25", "v":"13.647", "type":"H"}
     ]}"#;
 
     #[test]
     fn json_parse() {
-        let p: Predictions = serde_json::from_str(predicted_json_data).unwrap();
+        let p: Predictions = serde_json::from_str(PREDICTED_JSON_DATA).unwrap();
         println!("{:?}", p);
 
         let tidepoints: Vec<TidePoint> = p
@@ -592,8 +428,150 @@ mod tests {
         //let dt = DateTime::from_utc(today, *n.offset());
 
         println!("today: {} utc-dt: {}", today, n);
-        let chart_points = coordinates_from_prediction(tidepoints, n);
+        let chart_points = coordinates_from_prediction(tidepoints.clone(), n);
         println!("{:?}", chart_points);
+
+        assert_eq!(tidepoints.len(), 11);
+        assert_eq!(
+            tidepoints[0],
+            TidePoint::new(
+                parse_noaa_time("2022-01-10 05:05").unwrap(),
+                5.086,
+                Tide::Low
+            )
+        );
+        assert_eq!(tidepoints[1].tide(), Tide::High);
+        assert_eq!(tidepoints[10].level(), 0.666);
+        assert_curve_invariants(&tidepoints, n, &chart_points);
+    }
+
+    fn tide_points(raw: &str) -> Vec<TidePoint> {
+        let p: Predictions = serde_json::from_str(raw).unwrap();
+        p.tide_points()
+    }
+
+    /// Check the chart curve generated from `tv` for `today`:
+    ///  - x increases steadily and stays within the chart's +-12 hour margin
+    ///  - every prediction in range is a point on the curve
+    ///  - between consecutive predictions y moves monotonically from one to
+    ///    the other, in small steps
+    fn assert_curve_invariants(tv: &[TidePoint], today: DateTime<Utc>, xys: &[(f32, f32)]) {
+        let in_range: Vec<&TidePoint> = tv
+            .iter()
+            .filter(|t| t.dt > today - Duration::hours(12) && t.dt < today + Duration::hours(36))
+            .collect();
+        if in_range.len() < 2 {
+            return;
+        }
+
+        assert!(xys.windows(2).all(|w| w[0].0 < w[1].0), "x must increase");
+        assert!(xys.iter().all(|(x, _)| *x > -12f32 && *x < 36f32));
+
+        let hours = |t: &TidePoint| (t.dt - today).num_minutes() as f32 / 60f32;
+        let mut idx = Vec::new();
+        for t in in_range.iter() {
+            let i = xys
+                .iter()
+                .position(|(x, y)| (x - hours(t)).abs() < 1e-4 && *y == t.level)
+                .unwrap_or_else(|| panic!("curve misses {}", t.to_string()));
+            idx.push(i);
+        }
+        assert_eq!(idx[0], 0);
+        assert_eq!(*idx.last().unwrap(), xys.len() - 1);
+
+        for (pair, ends) in in_range.windows(2).zip(idx.windows(2)) {
+            let segment = &xys[ends[0]..=ends[1]];
+            let delta = pair[1].level - pair[0].level;
+            for w in segment.windows(2) {
+                let dy = w[1].1 - w[0].1;
+                assert!(dy * delta >= 0f32, "curve must not turn back between tides");
+                // A half cosine in CURVE_STEPS steps never jumps by more than
+                // pi / 2 / CURVE_STEPS of the full swing.
+                assert!(
+                    dy.abs() <= delta.abs() * 0.07 + 1e-4,
+                    "curve must be continuous"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn curve_invariants_each_day() {
+        let tv = tide_points(PREDICTION);
+        for day in ["2022-05-27 00:00", "2022-05-28 00:00", "2022-05-29 00:00"] {
+            let today = parse_noaa_time(day).unwrap();
+            let xys = coordinates_from_prediction(tv.clone(), today);
+            assert_curve_invariants(&tv, today, &xys);
+        }
+    }
+
+    #[test]
+    fn curve_continues_across_midnight() {
+        let today = parse_noaa_time("2022-05-28 00:00").unwrap();
+        let tv = vec![
+            TidePoint::new(
+                parse_noaa_time("2022-05-28 18:42").unwrap(),
+                12.991,
+                Tide::High,
+            ),
+            TidePoint::new(
+                parse_noaa_time("2022-05-29 00:00").unwrap(),
+                6.817,
+                Tide::Low,
+            ),
+            TidePoint::new(
+                parse_noaa_time("2022-05-29 04:44").unwrap(),
+                13.355,
+                Tide::High,
+            ),
+        ];
+        let xys = coordinates_from_prediction(tv.clone(), today);
+        assert_eq!(xys.last().unwrap().0, 24f32 + 4f32 + 44f32 / 60f32);
+        assert!(xys.contains(&(24f32, 6.817)));
+        assert_curve_invariants(&tv, today, &xys);
+    }
+
+    #[test]
+    fn level_at_follows_curve() {
+        let tv = tide_points(PREDICTION);
+        let today = parse_noaa_time("2022-05-28 00:00").unwrap();
+        for (x, y) in coordinates_from_prediction(tv.clone(), today) {
+            let dt = today + Duration::seconds((x * 3600f32).round() as i64);
+            assert!((level_at(&tv, dt).unwrap() - y).abs() < 1e-2);
+        }
+        assert_eq!(level_at(&tv, tv[3].dt()), Some(5.967));
+        assert_eq!(level_at(&tv, tv[0].dt() - Duration::minutes(1)), None);
+        assert_eq!(level_at(&tv, tv[10].dt() + Duration::minutes(1)), None);
+    }
+
+    /// Alternating highs and lows spaced 1-12 hours apart, starting the day
+    /// before `2022-05-28`.
+    fn arb_predictions() -> impl Strategy<Value = Vec<TidePoint>> {
+        prop::collection::vec((60i64..720, 0f32..10f32), 2..16).prop_map(|steps| {
+            let mut dt = parse_noaa_time("2022-05-27 10:00").unwrap();
+            steps
+                .into_iter()
+                .enumerate()
+                .map(|(i, (minutes, swing))| {
+                    dt += Duration::minutes(minutes);
+                    let (level, tide) = if i % 2 == 0 {
+                        (4f32 + swing, Tide::High)
+                    } else {
+                        (4f32 - swing, Tide::Low)
+                    };
+                    TidePoint::new(dt, level, tide)
+                })
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn curve_invariants_random(tv in arb_predictions()) {
+            let today = parse_noaa_time("2022-05-28 00:00").unwrap();
+            let xys = coordinates_from_prediction(tv.clone(), today);
+            assert_curve_invariants(&tv, today, &xys);
+        }
     }
 
     #[test]
@@ -0,0 +1,342 @@
+//! JavaScript facade: the `#[wasm_bindgen]` types the web front end uses to
+//! draw charts on canvas elements.
+use crate::alerts;
+use crate::clearance::{self, Profile};
+use crate::expr::Expr;
+use crate::func_plot::{self, Function};
+use crate::mandelbrot::{self, Fractal, Palette, View};
+use crate::plot3d::{self, Grid};
+use crate::shape::Shape;
+use crate::surge::Weather;
+use crate::tides::{self, *};
+use crate::CoordMap;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+// Use `wee_alloc` as the global allocator in the browser.
+#[cfg(target_arch = "wasm32")]
+#[global_allocator]
+static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+
+// Call the JS alert() callback.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+extern "C" {
+    unsafe fn alert(s: &str);
+}
+
+/// Type used on the JS side to convert screen coordinates to chart
+/// coordinates.
+#[wasm_bindgen]
+pub struct Chart {
+    convert: Box<dyn CoordMap<f64>>,
+}
+
+/// Result of screen to chart coordinates conversion.
+#[wasm_bindgen]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[wasm_bindgen]
+impl Chart {
+    // Draw Tides on provided canvas element
+    // Return Chart struct suitable for Coordinate conversion from (f32, f32)...?
+    pub fn tides(canvas_id: &str, raw_tides: &str, draw_cw: bool) -> Result<Chart, JsValue> {
+        let p: Predictions = serde_json::from_str(raw_tides).unwrap();
+        let tv: Vec<TidePoint> = p.tide_points();
+        let cw = Shape::charlie_wells();
+        let marker = if draw_cw { Some(&cw) } else { None };
+        let map_coord = tides::draw(canvas_id, tv, marker, None).map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+        })
+    }
+
+    /// Draw Tides with a custom marker at the current tide level. The marker
+    /// is a shape in JSON form or a simple SVG document.
+    pub fn tides_with_marker(
+        canvas_id: &str,
+        raw_tides: &str,
+        raw_marker: &str,
+    ) -> Result<Chart, JsValue> {
+        let p: Predictions = serde_json::from_str(raw_tides).map_err(|err| err.to_string())?;
+        let tv: Vec<TidePoint> = p.tide_points();
+        let marker = Shape::parse(raw_marker).map_err(|err| err.to_string())?;
+        let map_coord =
+            tides::draw(canvas_id, tv, Some(&marker), None).map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+        })
+    }
+
+    /// Draw Tides adjusted for storm surge and barometric pressure. The
+    /// adjustment is a CSV of `t,surge,pressure` rows, see `Weather::from_csv`.
+    pub fn tides_adjusted(
+        canvas_id: &str,
+        raw_tides: &str,
+        raw_weather: &str,
+        draw_cw: bool,
+    ) -> Result<Chart, JsValue> {
+        let p: Predictions = serde_json::from_str(raw_tides).map_err(|err| err.to_string())?;
+        let tv: Vec<TidePoint> = p.tide_points();
+        let weather = Weather::from_csv(raw_weather).map_err(|err| err.to_string())?;
+        let cw = Shape::charlie_wells();
+        let marker = if draw_cw { Some(&cw) } else { None };
+        let map_coord =
+            tides::draw(canvas_id, tv, marker, Some(&weather)).map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+        })
+    }
+
+    /// Upcoming alerts for the given rules (one per line, see
+    /// `alerts::Rule`) as a JSON array of `{rule, start, end, level}`.
+    pub fn alerts(raw_tides: &str, raw_rules: &str) -> Result<String, JsValue> {
+        let p: Predictions = serde_json::from_str(raw_tides).map_err(|err| err.to_string())?;
+        let tv: Vec<TidePoint> = p.tide_points();
+        let rules = alerts::parse_rules(raw_rules).map_err(|err| err.to_string())?;
+        let found =
+            alerts::evaluate(&rules, &tv, tides::station_now()).map_err(|err| err.to_string())?;
+        Ok(alerts::to_json(&found))
+    }
+
+    /// Draw today's under-keel clearance along the ferry route for a vessel
+    /// of `draft` feet. `raw_profile` is a CSV of charted depths along the
+    /// route with a `distance,depth` header, both in feet.
+    pub fn clearance(
+        canvas_id: &str,
+        raw_tides: &str,
+        raw_profile: &str,
+        draft: f32,
+    ) -> Result<Chart, JsValue> {
+        let p: Predictions = serde_json::from_str(raw_tides).map_err(|err| err.to_string())?;
+        let tv: Vec<TidePoint> = p.tide_points();
+        let profile = Profile::from_csv(raw_profile).map_err(|err| err.to_string())?;
+        let map_coord =
+            clearance::draw(canvas_id, tv, &profile, draft).map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+        })
+    }
+
+    /// Draw the power function f(x) = x^power on the canvas with id
+    /// `canvas_id`.
+    pub fn power(canvas_id: &str, power: i32) -> Result<Chart, JsValue> {
+        let f = format!("x^{}", power)
+            .parse::<Function>()
+            .map_err(|err| err.to_string())?;
+        let map_coord = func_plot::draw(canvas_id, &[f], -1f32..1f32, Some(-1.2f32..1.2f32))
+            .map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+        })
+    }
+
+    /// Plot one or more functions of `x`, one per line or separated by `;`,
+    /// e.g. `sin(x); 0.5 * x^2`, over `x_from..x_to`. The y axis is fitted
+    /// to the curves unless both `y_from` and `y_to` are given.
+    pub fn plot(
+        canvas_id: &str,
+        functions: &str,
+        x_from: f64,
+        x_to: f64,
+        y_from: Option<f64>,
+        y_to: Option<f64>,
+    ) -> Result<Chart, JsValue> {
+        let functions = func_plot::parse_functions(functions).map_err(|err| err.to_string())?;
+        let y_range = match (y_from, y_to) {
+            (Some(from), Some(to)) => Some(from as f32..to as f32),
+            _ => None,
+        };
+        let map_coord = func_plot::draw(canvas_id, &functions, x_from as f32..x_to as f32, y_range)
+            .map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(move |coord| map_coord(coord).map(|(x, y)| (x.into(), y.into()))),
+        })
+    }
+
+    /// Draw the Mandelbrot set on the provided canvas element.
+    pub fn mandelbrot(canvas: HtmlCanvasElement) -> Result<Chart, JsValue> {
+        let map_coord = mandelbrot::draw(canvas).map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(map_coord),
+        })
+    }
+
+    /// Draw a 3D surface plot viewed from the given pitch and yaw. A 3D
+    /// projection can't be mapped back to a single chart point, so `coord`
+    /// on the returned chart always gives `None`.
+    pub fn plot3d(canvas: HtmlCanvasElement, pitch: f64, yaw: f64) -> Result<Chart, JsValue> {
+        let surface = Surface::expression("cos(x^2 + z^2)", -3.0, 3.0, -3.0, 3.0)?;
+        Chart::surface(canvas, &surface, pitch, yaw)
+    }
+
+    /// Draw `surface` viewed from `pitch` and `yaw`, in radians. Redraw with
+    /// new angles as the mouse drags to rotate it. As with `plot3d`, `coord`
+    /// on the returned chart always gives `None`.
+    pub fn surface(
+        canvas: HtmlCanvasElement,
+        surface: &Surface,
+        pitch: f64,
+        yaw: f64,
+    ) -> Result<Chart, JsValue> {
+        plot3d::draw(canvas, &surface.grid, surface.color_by_height, pitch, yaw)
+            .map_err(|err| err.to_string())?;
+        Ok(Chart {
+            convert: Box::new(|_| None),
+        })
+    }
+
+    /// This function can be used to convert screen coordinates to
+    /// chart coordinates.
+    pub fn coord(&self, x: i32, y: i32) -> Option<Point> {
+        (self.convert)((x, y)).map(|(x, y)| Point { x, y })
+    }
+}
+
+/// Surface for `Chart::surface`, parsed once so it can be redrawn cheaply
+/// while the user rotates it.
+#[wasm_bindgen]
+pub struct Surface {
+    grid: Grid,
+    color_by_height: bool,
+}
+
+#[wasm_bindgen]
+impl Surface {
+    /// Surface `y = f(x, z)` over the given ranges, e.g. `cos(x^2 + z^2)`.
+    pub fn expression(
+        expression: &str,
+        x_from: f64,
+        x_to: f64,
+        z_from: f64,
+        z_to: f64,
+    ) -> Result<Surface, JsValue> {
+        let expr = expression.parse::<Expr>().map_err(|err| err.to_string())?;
+        let grid =
+            Grid::from_expr(&expr, x_from..x_to, z_from..z_to).map_err(|err| err.to_string())?;
+        Ok(Surface {
+            grid,
+            color_by_height: false,
+        })
+    }
+
+    /// Surface from a CSV grid with an `x,z,y` or `x,z,depth` header, such
+    /// as a bathymetry survey of the channel. Colored by height by default.
+    pub fn csv(raw: &str) -> Result<Surface, JsValue> {
+        let grid = Grid::from_csv(raw).map_err(|err| err.to_string())?;
+        Ok(Surface {
+            grid,
+            color_by_height: true,
+        })
+    }
+
+    /// Limit the surface to the given x and z ranges.
+    pub fn crop(&mut self, x_from: f64, x_to: f64, z_from: f64, z_to: f64) -> Result<(), JsValue> {
+        self.grid = self
+            .grid
+            .crop(x_from..x_to, z_from..z_to)
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    pub fn set_color_by_height(&mut self, on: bool) {
+        self.color_by_height = on;
+    }
+}
+
+/// Zoomable Mandelbrot/Julia explorer drawing on a canvas element. Mouse
+/// positions are canvas pixel coordinates, as for `Chart::coord`.
+#[wasm_bindgen]
+pub struct Explorer {
+    canvas: HtmlCanvasElement,
+    view: View,
+    /// First row not yet drawn by `draw_tile`.
+    next_row: u32,
+}
+
+#[wasm_bindgen]
+impl Explorer {
+    pub fn new(canvas: HtmlCanvasElement) -> Explorer {
+        let view = View::new(canvas.width(), canvas.height(), Fractal::Mandelbrot);
+        Explorer {
+            canvas,
+            view,
+            next_row: 0,
+        }
+    }
+
+    /// Draw the whole view in one go.
+    pub fn draw(&mut self) -> Result<(), JsValue> {
+        mandelbrot::draw_view(self.canvas.clone(), &self.view).map_err(|err| err.to_string())?;
+        self.next_row = self.view.size().1;
+        Ok(())
+    }
+
+    /// Draw the next band of `TILE_ROWS` rows, returning `true` once the
+    /// whole view is on the canvas. Call it from `requestAnimationFrame`
+    /// to keep the page responsive; any change to the view starts over.
+    pub fn draw_tile(&mut self) -> Result<bool, JsValue> {
+        let rows = self.next_row..self.next_row + mandelbrot::TILE_ROWS;
+        mandelbrot::draw_rows(self.canvas.clone(), &self.view, rows.clone())
+            .map_err(|err| err.to_string())?;
+        self.next_row = rows.end.min(self.view.size().1);
+        Ok(self.next_row == self.view.size().1)
+    }
+
+    /// Pick up a change in the canvas size.
+    pub fn resize(&mut self) {
+        self.view.resize(self.canvas.width(), self.canvas.height());
+        self.next_row = 0;
+    }
+
+    /// Zoom in by `factor` (below 1 zooms out) around the pixel `(x, y)`.
+    pub fn zoom_at(&mut self, x: i32, y: i32, factor: f64) {
+        self.view.zoom_at(x, y, factor);
+        self.next_row = 0;
+    }
+
+    /// Move the view by a mouse drag of `(dx, dy)` pixels.
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        self.view.pan(dx, dy);
+        self.next_row = 0;
+    }
+
+    pub fn home(&mut self) {
+        self.view.home();
+        self.next_row = 0;
+    }
+
+    pub fn mandelbrot(&mut self) {
+        self.view.set_fractal(Fractal::Mandelbrot);
+        self.next_row = 0;
+    }
+
+    /// Switch to the Julia set for `c = re + im i`.
+    pub fn julia(&mut self, re: f64, im: f64) {
+        self.view.set_fractal(Fractal::Julia(re, im));
+        self.next_row = 0;
+    }
+
+    /// One of `rainbow`, `fire`, `ocean` or `grayscale`.
+    pub fn set_palette(&mut self, name: &str) -> Result<(), JsValue> {
+        self.view.palette = name.parse::<Palette>().map_err(|err| err.to_string())?;
+        self.next_row = 0;
+        Ok(())
+    }
+
+    pub fn coord(&self, x: i32, y: i32) -> Point {
+        let (x, y) = self.view.coord(x, y);
+        Point { x, y }
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.view.zoom()
+    }
+
+    pub fn max_iter(&self) -> usize {
+        self.view.max_iter()
+    }
+}
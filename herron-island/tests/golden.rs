@@ -0,0 +1,86 @@
+//! Golden image tests for the tide chart, drawn on the native bitmap backend.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the images in `tests/golden` after an
+//! intentional change to the chart.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use chrono::NaiveDateTime;
+use herron_island::shape::Shape;
+use herron_island::surge::Weather;
+use herron_island::tides::{draw_on, Predictions, TidePoint};
+use plotters::prelude::*;
+use std::path::PathBuf;
+
+const SIZE: (u32, u32) = (800, 400);
+
+const PREDICTION: &str = r#"{ "predictions" : [{"t":"2022-05-27 03:49", "v":"14.036", "type":"H"},{"t":"2022-05-27 11:02", "v":"-0.058", "type":"L"},{"t":"2022-05-27 17:51", "v":"12.111", "type":"H"},{"t":"2022-05-27 23:02", "v":"5.967", "type":"L"},{"t":"2022-05-28 04:17", "v":"13.733", "type":"H"},{"t":"2022-05-28 11:32", "v":"-0.922", "type":"L"},{"t":"2022-05-28 18:42", "v":"12.991", "type":"H"},{"t":"2022-05-28 23:53", "v":"6.817", "type":"L"},{"t":"2022-05-29 04:44", "v":"13.355", "type":"H"},{"t":"2022-05-29 12:01", "v":"-1.516", "type":"L"},{"t":"2022-05-29 19:25", "v":"13.647", "type":"H"}
+    ]}"#;
+
+const WEATHER: &str = "t,surge,pressure
+2022-05-28 00:00,0.0,1013
+2022-05-28 09:00,1.5,990
+2022-05-28 18:00,0.5,1000
+";
+
+fn tide_points() -> Vec<TidePoint> {
+    let p: Predictions = serde_json::from_str(PREDICTION).unwrap();
+    p.tide_points()
+}
+
+fn now() -> NaiveDateTime {
+    NaiveDateTime::parse_from_str("2022-05-28 09:30", "%Y-%m-%d %H:%M").unwrap()
+}
+
+/// Compare `buf` against the named golden image. Text is rendered with the
+/// system's fonts, so a small fraction of differing pixels is tolerated.
+fn assert_golden(name: &str, buf: Vec<u8>) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+        .iter()
+        .collect();
+    let actual = image::RgbImage::from_raw(SIZE.0, SIZE.1, buf).unwrap();
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        actual.save(&path).unwrap();
+        return;
+    }
+
+    let expected = image::open(&path)
+        .unwrap_or_else(|e| panic!("cannot open {:?} ({}), run with UPDATE_GOLDEN=1", path, e))
+        .to_rgb8();
+    assert_eq!(expected.dimensions(), actual.dimensions());
+
+    let differing = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(e, a)| e.0.iter().zip(a.0.iter()).any(|(e, a)| e.abs_diff(*a) > 16))
+        .count();
+    let ratio = differing as f64 / (SIZE.0 * SIZE.1) as f64;
+    assert!(
+        ratio < 0.01,
+        "{}: {:.2}% of pixels differ",
+        name,
+        ratio * 100.0
+    );
+}
+
+#[test]
+fn tides_with_charlie_wells() {
+    let mut buf = vec![0u8; (SIZE.0 * SIZE.1 * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buf, SIZE).into_drawing_area();
+        let cw = Shape::charlie_wells();
+        let _ = draw_on(root, now(), tide_points(), Some(&cw), None).unwrap();
+    }
+    assert_golden("tides.png", buf);
+}
+
+#[test]
+fn tides_weather_adjusted() {
+    let mut buf = vec![0u8; (SIZE.0 * SIZE.1 * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buf, SIZE).into_drawing_area();
+        let weather = Weather::from_csv(WEATHER).unwrap();
+        let _ = draw_on(root, now(), tide_points(), None, Some(&weather)).unwrap();
+    }
+    assert_golden("tides-adjusted.png", buf);
+}
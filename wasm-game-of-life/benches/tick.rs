@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use wasm_game_of_life::{Kernel, Universe};
+
+fn tick(c: &mut Criterion) {
+    // CellByCell stands in for the original kernel, which kept a
+    // `Vec<Cell>` and counted neighbours with modulo arithmetic. It counts
+    // the same way, one neighbour at a time, but through the topology and
+    // the bit grid, so its times are not exactly the original's. Parallel
+    // is plain Bitwise unless built with the `parallel` feature, so it is
+    // only measured then.
+    let mut kernels = vec![Kernel::CellByCell, Kernel::Bitwise];
+    if cfg!(feature = "parallel") {
+        kernels.push(Kernel::Parallel);
+    }
+    let mut group = c.benchmark_group("tick");
+    for &side in &[64, 256, 1024] {
+        for &kernel in &kernels {
+            let mut universe = Universe::random(side, side, 0.3, 1);
+            universe.set_kernel(kernel);
+            universe.set_history_limit(0);
+            let id = BenchmarkId::new(format!("{:?}", kernel), side);
+            group.bench_function(id, |b| b.iter(|| universe.tick()));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tick);
+criterion_main!(benches);
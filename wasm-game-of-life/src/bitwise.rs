@@ -0,0 +1,244 @@
+use crate::grid::BitGrid;
+use crate::rule::Rule;
+use crate::Topology;
+
+/// Bits per word of a `BitGrid` row.
+const WORD: u32 = 32;
+
+/// The rows read above the top and below the bottom of the board when
+/// they aren't just its other edge, kept between generations so `step`
+/// doesn't allocate.
+#[derive(Clone, Debug, Default)]
+pub struct Halo {
+    above: Vec<u32>,
+    below: Vec<u32>,
+}
+
+/// Write the generation after `cells` into `next` under `rule`, a two
+/// state rule, 32 cells at a time: the eight neighbours of every cell in a
+/// word are summed at once as 4 bit counters sliced across 4 words. With
+/// `parallel` and the `parallel` feature, rows are shared out with rayon.
+pub fn step(
+    cells: &BitGrid,
+    next: &mut BitGrid,
+    halo: &mut Halo,
+    rule: &Rule,
+    topology: Topology,
+    parallel: bool,
+) {
+    let (width, height) = (cells.width(), cells.height());
+    if width == 0 || height == 0 {
+        return;
+    }
+    let wrap = topology == Topology::Torus || topology == Topology::KleinBottle;
+    let (above, below) = match topology {
+        Topology::Torus => (cells.row(height - 1), cells.row(0)),
+        Topology::KleinBottle => {
+            mirror_into(cells, height - 1, &mut halo.above);
+            mirror_into(cells, 0, &mut halo.below);
+            (&halo.above[..], &halo.below[..])
+        }
+        Topology::DeadEdge | Topology::Growing => {
+            blank_into(cells, &mut halo.above);
+            blank_into(cells, &mut halo.below);
+            (&halo.above[..], &halo.below[..])
+        }
+    };
+    let counts = Counts::new(rule);
+    let step_row = |row: u32, out: &mut [u32]| {
+        let up = if row == 0 { above } else { cells.row(row - 1) };
+        let down = if row == height - 1 {
+            below
+        } else {
+            cells.row(row + 1)
+        };
+        let rows = [up, cells.row(row), down];
+        for (i, word) in out.iter_mut().enumerate() {
+            *word = counts.next(&rows, i, width, wrap);
+        }
+    };
+
+    #[cfg(feature = "parallel")]
+    {
+        if parallel {
+            use rayon::prelude::*;
+            next.par_rows_mut()
+                .enumerate()
+                .for_each(|(row, out)| step_row(row as u32, out));
+            return;
+        }
+    }
+    #[cfg(not(feature = "parallel"))]
+    let _ = parallel;
+    for (row, out) in next.rows_mut().enumerate() {
+        step_row(row as u32, out);
+    }
+}
+
+/// Fill `words` with a row of dead cells as wide as `cells`.
+fn blank_into(cells: &BitGrid, words: &mut Vec<u32>) {
+    words.clear();
+    words.resize(cells.row(0).len(), 0);
+}
+
+/// Fill `words` with `row` read right to left, for the Klein bottle's
+/// twisted edge.
+fn mirror_into(cells: &BitGrid, row: u32, words: &mut Vec<u32>) {
+    let width = cells.width();
+    blank_into(cells, words);
+    for col in (0..width).filter(|&col| cells.get(row, width - 1 - col)) {
+        words[(col / WORD) as usize] |= 1 << (col % WORD);
+    }
+}
+
+/// Neighbour counts that give birth or survival under a rule, bit `n` set
+/// for a count of `n`.
+struct Counts {
+    birth: u16,
+    survival: u16,
+}
+
+impl Counts {
+    fn new(rule: &Rule) -> Counts {
+        let mask = |gives: fn(&Rule, u8) -> bool| {
+            (0..=8)
+                .filter(|&n| gives(rule, n))
+                .fold(0, |mask, n| mask | 1 << n)
+        };
+        Counts {
+            birth: mask(Rule::is_birth),
+            survival: mask(Rule::is_survival),
+        }
+    }
+
+    /// Next generation of word `i` of the middle of `rows`, the rows above,
+    /// at and below it.
+    fn next(&self, rows: &[&[u32]; 3], i: usize, width: u32, wrap: bool) -> u32 {
+        let [up, row, down] = *rows;
+        let (up_left, up_right) = sides(up, i, width, wrap);
+        let (left, right) = sides(row, i, width, wrap);
+        let (down_left, down_right) = sides(down, i, width, wrap);
+        let neighbors = [
+            up_left, up[i], up_right, left, right, down_left, down[i], down_right,
+        ];
+
+        // Bit `b` of each cell's count is in `planes[b]`.
+        let mut planes = [0u32; 4];
+        for &neighbor in &neighbors {
+            let mut carry = neighbor;
+            for plane in planes.iter_mut() {
+                let next_carry = *plane & carry;
+                *plane ^= carry;
+                carry = next_carry;
+            }
+        }
+        let count_is = |n: u32| {
+            planes.iter().enumerate().fold(!0, |mask, (b, &plane)| {
+                mask & if n >> b & 1 == 1 { plane } else { !plane }
+            })
+        };
+        let any_of = |counts: u16| {
+            (0..=8)
+                .filter(|&n| counts >> n & 1 == 1)
+                .fold(0, |m, n| m | count_is(n))
+        };
+        let born = any_of(self.birth);
+        let survives = any_of(self.survival);
+        let live = row[i];
+        let mut next = (born & !live) | (survives & live);
+        if i == row.len() - 1 {
+            // Clear the bits past the end of the row.
+            next &= !0 >> (row.len() as u32 * WORD - width);
+        }
+        next
+    }
+}
+
+/// The cells left and right of each cell in word `i` of `row`. Past the
+/// ends of the row they wrap around with `wrap`, otherwise are dead.
+fn sides(row: &[u32], i: usize, width: u32, wrap: bool) -> (u32, u32) {
+    let last = row.len() - 1;
+    let bit = |col: u32| row[(col / WORD) as usize] >> (col % WORD) & 1;
+    let before = match i {
+        0 if wrap => bit(width - 1),
+        0 => 0,
+        _ => row[i - 1] >> (WORD - 1),
+    };
+    let left = row[i] << 1 | before;
+    let right = if i < last {
+        row[i] >> 1 | (row[i + 1] & 1) << (WORD - 1)
+    } else if wrap {
+        row[i] >> 1 | bit(0) << ((width - 1) % WORD)
+    } else {
+        row[i] >> 1
+    };
+    (left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bitwise::*;
+    use crate::rng::SplitMix64;
+
+    /// Cell by cell reference, reading neighbours through each topology.
+    fn reference(cells: &BitGrid, rule: &Rule, topology: Topology) -> BitGrid {
+        let (width, height) = (cells.width() as i64, cells.height() as i64);
+        let live = |row: i64, col: i64| {
+            let on_board = (0..height).contains(&row) && (0..width).contains(&col);
+            let (row, col) = match topology {
+                Topology::Torus => (row.rem_euclid(height), col.rem_euclid(width)),
+                Topology::KleinBottle if (0..height).contains(&row) => (row, col.rem_euclid(width)),
+                Topology::KleinBottle => {
+                    (row.rem_euclid(height), (width - 1 - col).rem_euclid(width))
+                }
+                _ if on_board => (row, col),
+                _ => return 0,
+            };
+            cells.get(row as u32, col as u32) as u8
+        };
+        let mut next = BitGrid::new(cells.width(), cells.height());
+        for row in 0..height {
+            for col in 0..width {
+                let count = (-1..=1)
+                    .flat_map(|dr| (-1..=1).map(move |dc| (dr, dc)))
+                    .filter(|&d| d != (0, 0))
+                    .map(|(dr, dc)| live(row + dr, col + dc))
+                    .sum();
+                let state = rule.next(cells.get(row as u32, col as u32) as u8, count);
+                next.set(row as u32, col as u32, state == 1);
+            }
+        }
+        next
+    }
+
+    #[test]
+    fn matches_cell_by_cell() {
+        let mut rng = SplitMix64::new(47);
+        let mut halo = Halo::default();
+        let topologies = [Topology::Torus, Topology::DeadEdge, Topology::KleinBottle];
+        let rules = ["B3/S23", "B36/S23", "B0/S8", "B1357/S1357"];
+        for &(width, height) in &[(1, 1), (3, 2), (31, 5), (32, 4), (33, 7), (70, 9)] {
+            let mut cells = BitGrid::new(width, height);
+            for row in 0..height {
+                for col in 0..width {
+                    cells.set(row, col, rng.next_f64() < 0.4);
+                }
+            }
+            for &topology in &topologies {
+                for rule in rules.iter().map(|r| r.parse::<Rule>().unwrap()) {
+                    let mut next = BitGrid::new(width, height);
+                    step(&cells, &mut next, &mut halo, &rule, topology, true);
+                    assert_eq!(
+                        next,
+                        reference(&cells, &rule, topology),
+                        "{} on a {}x{} {:?}",
+                        rule,
+                        width,
+                        height,
+                        topology
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+/// Bits per storage word.
+const WORD: u32 = 32;
+
+/// A bit per cell, packed into rows of whole `u32` words so every row
+/// starts on a word boundary.
+///
+/// Read as bytes (wasm is little endian) the cell at `row`, `column` is bit
+/// `column % 8` of byte `row * stride_bytes() + column / 8`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct BitGrid {
+    width: u32,
+    height: u32,
+    /// Words per row.
+    stride: usize,
+    words: Vec<u32>,
+}
+
+impl Clone for BitGrid {
+    fn clone(&self) -> BitGrid {
+        BitGrid {
+            words: self.words.clone(),
+            ..*self
+        }
+    }
+
+    /// Reuses this grid's buffer when it is big enough.
+    fn clone_from(&mut self, source: &BitGrid) {
+        self.width = source.width;
+        self.height = source.height;
+        self.stride = source.stride;
+        self.words.clone_from(&source.words);
+    }
+}
+
+impl BitGrid {
+    /// An all-clear grid.
+    pub fn new(width: u32, height: u32) -> BitGrid {
+        let stride = width.div_ceil(WORD) as usize;
+        BitGrid {
+            width,
+            height,
+            stride,
+            words: vec![0; stride * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Bytes from the start of one row to the next.
+    pub fn stride_bytes(&self) -> usize {
+        self.stride * (WORD / 8) as usize
+    }
+
+    pub fn as_ptr(&self) -> *const u8 {
+        self.words.as_ptr() as *const u8
+    }
+
+    fn word_bit(&self, row: u32, column: u32) -> (usize, u32) {
+        debug_assert!(row < self.height && column < self.width);
+        (
+            row as usize * self.stride + (column / WORD) as usize,
+            1 << (column % WORD),
+        )
+    }
+
+    pub fn get(&self, row: u32, column: u32) -> bool {
+        let (word, bit) = self.word_bit(row, column);
+        self.words[word] & bit != 0
+    }
+
+    pub fn set(&mut self, row: u32, column: u32, value: bool) {
+        let (word, bit) = self.word_bit(row, column);
+        if value {
+            self.words[word] |= bit;
+        } else {
+            self.words[word] &= !bit;
+        }
+    }
+
+    /// The words of `row`. Bits past `width` in the last word are clear.
+    pub fn row(&self, row: u32) -> &[u32] {
+        let start = row as usize * self.stride;
+        &self.words[start..start + self.stride]
+    }
+
+    pub fn rows_mut(&mut self) -> std::slice::ChunksMut<'_, u32> {
+        self.words.chunks_mut(self.stride)
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn par_rows_mut(&mut self) -> rayon::slice::ChunksMut<'_, u32> {
+        use rayon::slice::ParallelSliceMut;
+        self.words.par_chunks_mut(self.stride)
+    }
+
+    /// Number of set bits.
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|w| *w = 0);
+    }
+
+    /// Append `row * width + column` for each cell that differs from
+    /// `other`, a grid of the same size, in row order.
+    pub fn diff_into(&self, other: &BitGrid, indices: &mut Vec<u32>) {
+        debug_assert!(self.width == other.width && self.height == other.height);
+        let words = self.words.iter().zip(&other.words).enumerate();
+        for (i, (a, b)) in words.filter(|(_, (a, b))| a != b) {
+            let row = (i / self.stride) as u32;
+            let first = (i % self.stride) as u32 * WORD;
+            let mut bits = a ^ b;
+            while bits != 0 {
+                indices.push(row * self.width + first + bits.trailing_zeros());
+                bits &= bits - 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::*;
+
+    #[test]
+    fn bytes_match_the_documented_layout() {
+        let mut grid = BitGrid::new(40, 3);
+        assert_eq!(grid.stride_bytes(), 8);
+        grid.set(1, 0, true);
+        grid.set(1, 9, true);
+        grid.set(2, 39, true);
+        assert!(grid.get(1, 9) && !grid.get(1, 8));
+        assert_eq!(grid.count(), 3);
+
+        let bytes = unsafe { std::slice::from_raw_parts(grid.as_ptr(), 8 * 3) };
+        let lit: Vec<(usize, u32)> = (0..3)
+            .flat_map(|r| (0..40).map(move |c| (r, c)))
+            .filter(|&(r, c)| bytes[r * 8 + (c / 8) as usize] & (1 << (c % 8)) != 0)
+            .collect();
+        assert_eq!(lit, vec![(1, 0), (1, 9), (2, 39)]);
+
+        grid.clear();
+        assert!(!grid.get(1, 0) && !grid.get(2, 39));
+    }
+
+    #[test]
+    fn diff_lists_changed_cells() {
+        let mut a = BitGrid::new(40, 3);
+        let mut b = a.clone();
+        a.set(0, 3, true);
+        a.set(2, 33, true);
+        b.set(2, 33, true);
+        b.set(2, 39, true);
+        b.set(1, 0, true);
+        let mut changes = vec![7];
+        a.diff_into(&b, &mut changes);
+        assert_eq!(changes, [7, 3, 40, 2 * 40 + 39]);
+    }
+}
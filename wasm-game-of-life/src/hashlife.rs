@@ -0,0 +1,576 @@
+use crate::rule::Rule;
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Index of a node in `HashLife::nodes`.
+type NodeId = u32;
+
+const DEAD: NodeId = 0;
+const ALIVE: NodeId = 1;
+
+/// Smallest root, 8x8 cells.
+const MIN_LEVEL: u8 = 3;
+
+/// Largest root, `2^62` cells on a side. The root stays centred on the
+/// origin, so every coordinate inside it and its size fit in an `i64`.
+const MAX_LEVEL: u8 = 62;
+
+/// Largest log2 of the number of generations `step` advances at once. A
+/// root big enough for bigger steps wouldn't fit under `MAX_LEVEL`.
+pub const MAX_STEP: u8 = 48;
+
+/// Once this many nodes exist, drop everything the root doesn't use,
+/// along with the memoized results.
+const NODE_LIMIT: usize = 1 << 22;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Quad {
+    nw: NodeId,
+    ne: NodeId,
+    sw: NodeId,
+    se: NodeId,
+}
+
+/// A square of `2^level` cells on a side. Level 0 nodes are single cells.
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    level: u8,
+    quad: Quad,
+    population: u64,
+}
+
+/// Gosper's HashLife: the plane is a quadtree of hash-consed nodes and the
+/// future of each node is memoized, so repetitive patterns advance by
+/// huge steps in little time.
+///
+/// The plane is unbounded in practice: coordinates are `i64` rows and
+/// columns and the tree grows to cover whatever the pattern reaches. Only
+/// two state rules without `B0` can run.
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    index: HashMap<Quad, NodeId>,
+    /// `successor` results by node and log2 of the step.
+    results: HashMap<(NodeId, u8), NodeId>,
+    /// The empty node of each level.
+    empty: Vec<NodeId>,
+    root: NodeId,
+    /// Row and column of the root's top left cell.
+    top: i64,
+    left: i64,
+    generation: u64,
+}
+
+impl HashLife {
+    /// An empty plane under `rule`.
+    pub fn new(rule: Rule) -> Result<HashLife, Box<dyn Error>> {
+        check_rule(&rule)?;
+        let leaf = |population| Node {
+            level: 0,
+            quad: Quad {
+                nw: DEAD,
+                ne: DEAD,
+                sw: DEAD,
+                se: DEAD,
+            },
+            population,
+        };
+        let mut life = HashLife {
+            rule,
+            nodes: vec![leaf(0), leaf(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            top: 0,
+            left: 0,
+            generation: 0,
+        };
+        life.clear();
+        Ok(life)
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Switch rules, forgetting every memoized result.
+    pub fn set_rule(&mut self, rule: Rule) -> Result<(), Box<dyn Error>> {
+        check_rule(&rule)?;
+        self.rule = rule;
+        self.results.clear();
+        Ok(())
+    }
+
+    /// Kill every cell and reset the generation count.
+    pub fn clear(&mut self) {
+        self.root = self.empty(MIN_LEVEL);
+        self.top = -(1 << (MIN_LEVEL - 1));
+        self.left = self.top;
+        self.generation = 0;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u64 {
+        self.node(self.root).population
+    }
+
+    fn node(&self, n: NodeId) -> Node {
+        self.nodes[n as usize]
+    }
+
+    fn size(&self) -> i64 {
+        1 << self.node(self.root).level
+    }
+
+    fn contains(&self, row: i64, column: i64) -> bool {
+        let size = self.size();
+        (self.top..self.top + size).contains(&row)
+            && (self.left..self.left + size).contains(&column)
+    }
+
+    pub fn get(&self, row: i64, column: i64) -> bool {
+        if !self.contains(row, column) {
+            return false;
+        }
+        let (mut n, mut row, mut column) = (self.root, row - self.top, column - self.left);
+        loop {
+            let node = self.node(n);
+            if node.level == 0 || node.population == 0 {
+                return node.population != 0;
+            }
+            let half = 1 << (node.level - 1);
+            n = match (row < half, column < half) {
+                (true, true) => node.quad.nw,
+                (true, false) => node.quad.ne,
+                (false, true) => node.quad.sw,
+                (false, false) => node.quad.se,
+            };
+            row %= half;
+            column %= half;
+        }
+    }
+
+    /// Set a cell. Cells more than `2^61` from the origin are off the plane
+    /// and ignored.
+    pub fn set(&mut self, row: i64, column: i64, alive: bool) {
+        while !self.contains(row, column) {
+            if !self.expand() {
+                return;
+            }
+        }
+        self.root = self.set_in(self.root, row - self.top, column - self.left, alive);
+    }
+
+    fn set_in(&mut self, n: NodeId, row: i64, column: i64, alive: bool) -> NodeId {
+        let node = self.node(n);
+        if node.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = 1 << (node.level - 1);
+        let Quad {
+            mut nw,
+            mut ne,
+            mut sw,
+            mut se,
+        } = node.quad;
+        let (r, c) = (row % half, column % half);
+        match (row < half, column < half) {
+            (true, true) => nw = self.set_in(nw, r, c, alive),
+            (true, false) => ne = self.set_in(ne, r, c, alive),
+            (false, true) => sw = self.set_in(sw, r, c, alive),
+            (false, false) => se = self.set_in(se, r, c, alive),
+        }
+        self.join(nw, ne, sw, se)
+    }
+
+    /// Call `f(row, column)` for every live cell in the `height` by `width`
+    /// rectangle with its top left at `top`, `left`. Coordinates passed to
+    /// `f` are relative to the rectangle.
+    pub fn for_each_live(
+        &self,
+        top: i64,
+        left: i64,
+        height: u32,
+        width: u32,
+        f: &mut impl FnMut(u32, u32),
+    ) {
+        let view = (
+            top,
+            left,
+            top.saturating_add(height as i64),
+            left.saturating_add(width as i64),
+        );
+        self.visit(self.root, self.top, self.left, view, f);
+    }
+
+    fn visit(
+        &self,
+        n: NodeId,
+        top: i64,
+        left: i64,
+        view: (i64, i64, i64, i64),
+        f: &mut impl FnMut(u32, u32),
+    ) {
+        let node = self.node(n);
+        let size = 1i64 << node.level;
+        let (view_top, view_left, view_bottom, view_right) = view;
+        if node.population == 0
+            || top >= view_bottom
+            || left >= view_right
+            || top.saturating_add(size) <= view_top
+            || left.saturating_add(size) <= view_left
+        {
+            return;
+        }
+        if node.level == 0 {
+            f((top - view_top) as u32, (left - view_left) as u32);
+            return;
+        }
+        let half = size / 2;
+        self.visit(node.quad.nw, top, left, view, f);
+        self.visit(node.quad.ne, top, left + half, view, f);
+        self.visit(node.quad.sw, top + half, left, view, f);
+        self.visit(node.quad.se, top + half, left + half, view, f);
+    }
+
+    /// Advance `2^k` generations, with `k` at most `MAX_STEP`.
+    pub fn step(&mut self, k: u8) {
+        let k = k.min(MAX_STEP);
+        // Nothing moves faster than a cell a generation, so a pattern in the
+        // central quarter of a root at least `2^(k + 3)` wide stays inside
+        // the central half `successor` returns.
+        loop {
+            let level = self.node(self.root).level;
+            if level >= k + 3 {
+                let center = self.center(self.root);
+                let inner = self.center(center);
+                if self.node(inner).population == self.population() {
+                    break;
+                }
+            }
+            if !self.expand() {
+                // The plane ends here. Cells that have wandered this far out
+                // see dead cells past the edge, and `successor` drops any
+                // outside the root's middle half.
+                break;
+            }
+        }
+        let level = self.node(self.root).level;
+        self.root = self.successor(self.root, k);
+        self.top += 1 << (level - 2);
+        self.left += 1 << (level - 2);
+        self.generation = self.generation.saturating_add(1 << k);
+
+        if self.nodes.len() > NODE_LIMIT {
+            self.compact();
+        }
+    }
+
+    /// Advance `generations`, in power of two steps.
+    pub fn advance(&mut self, generations: u64) {
+        let mut left = generations;
+        while left > 0 {
+            let k = (63 - left.leading_zeros() as u8).min(MAX_STEP);
+            self.step(k);
+            left -= 1 << k;
+        }
+    }
+
+    fn join(&mut self, nw: NodeId, ne: NodeId, sw: NodeId, se: NodeId) -> NodeId {
+        let quad = Quad { nw, ne, sw, se };
+        if let Some(&n) = self.index.get(&quad) {
+            return n;
+        }
+        let population = [nw, ne, sw, se]
+            .iter()
+            .map(|&q| self.node(q).population)
+            .sum();
+        let n = self.nodes.len() as NodeId;
+        self.nodes.push(Node {
+            level: self.node(nw).level + 1,
+            quad,
+            population,
+        });
+        self.index.insert(quad, n);
+        n
+    }
+
+    fn empty(&mut self, level: u8) -> NodeId {
+        while self.empty.len() <= level as usize {
+            let e = *self.empty.last().unwrap();
+            let next = self.join(e, e, e, e);
+            self.empty.push(next);
+        }
+        self.empty[level as usize]
+    }
+
+    /// Double the root's size, keeping its cells in the middle. Returns
+    /// `false`, changing nothing, once the root is `MAX_LEVEL`.
+    fn expand(&mut self) -> bool {
+        let Node { level, quad, .. } = self.node(self.root);
+        if level >= MAX_LEVEL {
+            return false;
+        }
+        let e = self.empty(level - 1);
+        let nw = self.join(e, e, e, quad.nw);
+        let ne = self.join(e, e, quad.ne, e);
+        let sw = self.join(e, quad.sw, e, e);
+        let se = self.join(quad.se, e, e, e);
+        self.root = self.join(nw, ne, sw, se);
+        self.top -= 1 << (level - 1);
+        self.left -= 1 << (level - 1);
+        true
+    }
+
+    /// The half size node at the middle of `n`.
+    fn center(&mut self, n: NodeId) -> NodeId {
+        let q = self.node(n).quad;
+        let (nw, ne, sw, se) = (
+            self.node(q.nw).quad,
+            self.node(q.ne).quad,
+            self.node(q.sw).quad,
+            self.node(q.se).quad,
+        );
+        self.join(nw.se, ne.sw, sw.ne, se.nw)
+    }
+
+    /// The middle half of level `L` node `n` after `2^j` generations, where
+    /// `j <= L - 2`.
+    fn successor(&mut self, n: NodeId, j: u8) -> NodeId {
+        let node = self.node(n);
+        debug_assert!(node.level >= 2 && j <= node.level - 2);
+        if node.population == 0 {
+            return self.empty(node.level - 1);
+        }
+        if let Some(&r) = self.results.get(&(n, j)) {
+            return r;
+        }
+
+        let result = if node.level == 2 {
+            self.base(node)
+        } else {
+            let Quad { nw, ne, sw, se } = node.quad;
+            let (a, b, c, d) = (
+                self.node(nw).quad,
+                self.node(ne).quad,
+                self.node(sw).quad,
+                self.node(se).quad,
+            );
+            // Nine overlapping half size nodes covering `n`.
+            let parts = [
+                nw,
+                self.join(a.ne, b.nw, a.se, b.sw),
+                ne,
+                self.join(a.sw, a.se, c.nw, c.ne),
+                self.join(a.se, b.sw, c.ne, d.nw),
+                self.join(b.sw, b.se, d.nw, d.ne),
+                sw,
+                self.join(c.ne, d.nw, c.se, d.sw),
+                se,
+            ];
+            // A full `2^(L-2)` step advances both stages by half of it;
+            // shorter steps take the first stage as is.
+            let full = j == node.level - 2;
+            let mut r = [DEAD; 9];
+            for (result, &part) in r.iter_mut().zip(parts.iter()) {
+                *result = if full {
+                    self.successor(part, j - 1)
+                } else {
+                    self.center(part)
+                };
+            }
+            let rest = if full { j - 1 } else { j };
+            let q_nw = self.join(r[0], r[1], r[3], r[4]);
+            let q_ne = self.join(r[1], r[2], r[4], r[5]);
+            let q_sw = self.join(r[3], r[4], r[6], r[7]);
+            let q_se = self.join(r[4], r[5], r[7], r[8]);
+            let nw = self.successor(q_nw, rest);
+            let ne = self.successor(q_ne, rest);
+            let sw = self.successor(q_sw, rest);
+            let se = self.successor(q_se, rest);
+            self.join(nw, ne, sw, se)
+        };
+        self.results.insert((n, j), result);
+        result
+    }
+
+    /// One generation of the middle 2x2 of a 4x4 node.
+    fn base(&mut self, node: Node) -> NodeId {
+        let mut cells = [[false; 4]; 4];
+        let quads = [node.quad.nw, node.quad.ne, node.quad.sw, node.quad.se];
+        for (i, &q) in quads.iter().enumerate() {
+            let q = self.node(q).quad;
+            let leaves = [q.nw, q.ne, q.sw, q.se];
+            for (j, &leaf) in leaves.iter().enumerate() {
+                cells[i / 2 * 2 + j / 2][i % 2 * 2 + j % 2] = leaf == ALIVE;
+            }
+        }
+        let mut next = [DEAD; 4];
+        for (i, next) in next.iter_mut().enumerate() {
+            let (row, col) = (1 + i / 2, 1 + i % 2);
+            let block = cells[row - 1..=row + 1]
+                .iter()
+                .flat_map(|r| &r[col - 1..=col + 1]);
+            let neighbors = block.filter(|&&alive| alive).count() as u8 - cells[row][col] as u8;
+            if self.rule.next(cells[row][col] as u8, neighbors) == 1 {
+                *next = ALIVE;
+            }
+        }
+        self.join(next[0], next[1], next[2], next[3])
+    }
+
+    /// Rebuild the node table with only the nodes the root uses.
+    fn compact(&mut self) {
+        let mut fresh = HashLife {
+            rule: self.rule,
+            nodes: self.nodes[..2].to_vec(),
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            top: self.top,
+            left: self.left,
+            generation: self.generation,
+        };
+        let mut copied = HashMap::new();
+        fresh.root = fresh.copy_from(self, self.root, &mut copied);
+        *self = fresh;
+    }
+
+    fn copy_from(
+        &mut self,
+        from: &HashLife,
+        n: NodeId,
+        copied: &mut HashMap<NodeId, NodeId>,
+    ) -> NodeId {
+        if n == DEAD || n == ALIVE {
+            return n;
+        }
+        if let Some(&c) = copied.get(&n) {
+            return c;
+        }
+        let q = from.node(n).quad;
+        let nw = self.copy_from(from, q.nw, copied);
+        let ne = self.copy_from(from, q.ne, copied);
+        let sw = self.copy_from(from, q.sw, copied);
+        let se = self.copy_from(from, q.se, copied);
+        let c = self.join(nw, ne, sw, se);
+        copied.insert(n, c);
+        c
+    }
+}
+
+fn check_rule(rule: &Rule) -> Result<(), Box<dyn Error>> {
+    if rule.states() > 2 {
+        return Err(format!("HashLife can't run the Generations rule {}", rule).into());
+    }
+    if rule.is_birth(0) {
+        return Err(format!("HashLife can't run {}, B0 fills the infinite plane", rule).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hashlife::*;
+
+    fn glider(life: &mut HashLife) {
+        for &(r, c) in &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            life.set(r, c, true);
+        }
+    }
+
+    fn live(life: &HashLife, top: i64, left: i64, size: u32) -> Vec<(u32, u32)> {
+        let mut cells = Vec::new();
+        life.for_each_live(top, left, size, size, &mut |r, c| cells.push((r, c)));
+        cells.sort_unstable();
+        cells
+    }
+
+    #[test]
+    fn glider_travels() {
+        let mut life = HashLife::new(Rule::conway()).unwrap();
+        glider(&mut life);
+        let start = live(&life, 0, 0, 3);
+
+        // A glider moves one cell diagonally every four generations.
+        life.step(2);
+        assert_eq!(life.generation(), 4);
+        assert_eq!(live(&life, 1, 1, 3), start);
+
+        life.advance(4 * 1_000_000 - 4);
+        assert_eq!(life.population(), 5);
+        assert_eq!(live(&life, 1_000_000, 1_000_000, 3), start);
+        assert!(life.get(1_000_000, 1_000_001));
+
+        // Compacting keeps the pattern and drops the glider's history.
+        let nodes = life.nodes.len();
+        life.compact();
+        assert!(life.nodes.len() < nodes);
+        assert_eq!(live(&life, 1_000_000, 1_000_000, 3), start);
+        life.step(2);
+        assert_eq!(live(&life, 1_000_001, 1_000_001, 3), start);
+    }
+
+    #[test]
+    fn steps_match_single_generations() {
+        // R-pentomino, chaotic for over a thousand generations.
+        let cells = [(0, 1), (0, 2), (1, 0), (1, 1), (2, 1)];
+        let mut jumped = HashLife::new(Rule::conway()).unwrap();
+        let mut stepped = HashLife::new(Rule::conway()).unwrap();
+        for &(r, c) in &cells {
+            jumped.set(r, c, true);
+            stepped.set(r, c, true);
+        }
+        jumped.step(7);
+        for _ in 0..128 {
+            stepped.step(0);
+        }
+        assert_eq!(jumped.population(), stepped.population());
+        assert_eq!(live(&jumped, -64, -64, 128), live(&stepped, -64, -64, 128));
+    }
+
+    #[test]
+    fn huge_steps_stay_on_the_plane() {
+        let mut life = HashLife::new(Rule::conway()).unwrap();
+        glider(&mut life);
+        let start = live(&life, 0, 0, 3);
+        // Steps past `MAX_STEP` are taken as `MAX_STEP`.
+        life.step(64);
+        assert_eq!(life.generation(), 1 << MAX_STEP);
+        let far = 1 << (MAX_STEP - 2);
+        assert_eq!(live(&life, far, far, 3), start);
+        life.for_each_live(i64::MAX, i64::MAX, u32::MAX, u32::MAX, &mut |_, _| {
+            panic!("nothing lives at the end of i64")
+        });
+
+        // Cells off the plane are ignored, those near its edge die there.
+        life.clear();
+        life.set(i64::MIN, i64::MAX, true);
+        assert_eq!(life.population(), 0);
+        for &(r, c) in &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+            life.set((1 << 61) - 8 + r, (1 << 61) - 8 + c, true);
+        }
+        assert_eq!(life.population(), 5);
+        life.step(MAX_STEP);
+        assert_eq!(life.population(), 0);
+    }
+
+    #[test]
+    fn rejects_unsupported_rules() {
+        let err = |r: &str| HashLife::new(r.parse().unwrap()).err().unwrap().to_string();
+        assert_eq!(
+            err("B2/S345/C4"),
+            "HashLife can't run the Generations rule B2/S345/C4"
+        );
+        assert_eq!(
+            err("B03/S23"),
+            "HashLife can't run B03/S23, B0 fills the infinite plane"
+        );
+        assert!(HashLife::new("B36/S23".parse().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,182 @@
+use crate::grid::BitGrid;
+use crate::rule::Rule;
+use std::collections::VecDeque;
+
+/// Generations kept for stepping back until `History::set_limit` changes it.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// The universe as it was before a tick.
+pub struct Snapshot {
+    pub generation: u64,
+    pub rule: Rule,
+    pub cells: BitGrid,
+    pub ages: Vec<u8>,
+}
+
+/// One user edit, as `(row, column, before, after)` for each cell changed.
+struct Edit {
+    generation: u64,
+    cells: Vec<(u32, u32, u8, u8)>,
+}
+
+/// Recent generations for stepping back, in a ring of at most `limit`,
+/// and the undo and redo stacks of edits.
+pub struct History {
+    snapshots: VecDeque<Snapshot>,
+    limit: usize,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History {
+            snapshots: VecDeque::new(),
+            limit: DEFAULT_LIMIT,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+}
+
+impl History {
+    /// Number of generations that can be stepped back.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Keep at most `limit` generations, dropping the oldest.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.snapshots.len() > limit {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Forget every generation and edit.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Save the state before a tick. Once the ring is full the oldest
+    /// snapshot's buffers are reused, so this doesn't allocate.
+    pub fn push(&mut self, generation: u64, rule: Rule, cells: &BitGrid, ages: &[u8]) {
+        self.redo.clear();
+        if self.limit == 0 {
+            return;
+        }
+        let snapshot = if self.snapshots.len() >= self.limit {
+            let mut oldest = self.snapshots.pop_front().unwrap();
+            oldest.generation = generation;
+            oldest.rule = rule;
+            oldest.cells.clone_from(cells);
+            oldest.ages.clear();
+            oldest.ages.extend_from_slice(ages);
+            oldest
+        } else {
+            Snapshot {
+                generation,
+                rule,
+                cells: cells.clone(),
+                ages: ages.to_vec(),
+            }
+        };
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Take the latest snapshot, dropping edits made after it.
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        let snapshot = self.snapshots.pop_back()?;
+        let generation = snapshot.generation;
+        self.undo.retain(|e| e.generation <= generation);
+        self.redo.retain(|e| e.generation <= generation);
+        Some(snapshot)
+    }
+
+    /// Record an edit made at `generation`, as `(row, column, before,
+    /// after)` for each cell.
+    pub fn record(&mut self, generation: u64, cells: Vec<(u32, u32, u8, u8)>) {
+        self.redo.clear();
+        self.undo.push(Edit { generation, cells });
+    }
+
+    /// The `(row, column, state)` changes undoing the last edit, if it was
+    /// made at `generation`.
+    pub fn undo(&mut self, generation: u64) -> Option<Vec<(u32, u32, u8)>> {
+        match self.undo.last() {
+            Some(edit) if edit.generation == generation => {
+                let edit = self.undo.pop().unwrap();
+                let cells = edit.cells.iter().map(|&(r, c, before, _)| (r, c, before));
+                let cells = cells.collect();
+                self.redo.push(edit);
+                Some(cells)
+            }
+            _ => None,
+        }
+    }
+
+    /// The `(row, column, state)` changes redoing the last undone edit, if
+    /// it was made at `generation`.
+    pub fn redo(&mut self, generation: u64) -> Option<Vec<(u32, u32, u8)>> {
+        match self.redo.last() {
+            Some(edit) if edit.generation == generation => {
+                let edit = self.redo.pop().unwrap();
+                let cells = edit.cells.iter().map(|&(r, c, _, after)| (r, c, after));
+                let cells = cells.collect();
+                self.undo.push(edit);
+                Some(cells)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::history::*;
+
+    #[test]
+    fn ring_reuses_the_oldest_snapshot() {
+        let mut history = History::default();
+        history.set_limit(2);
+        let mut cells = BitGrid::new(4, 4);
+        for generation in 0..3 {
+            cells.set(0, generation as u32, true);
+            history.push(generation, Rule::conway(), &cells, &[]);
+        }
+        assert_eq!(history.len(), 2);
+        let latest = history.pop().unwrap();
+        assert_eq!(latest.generation, 2);
+        assert!(latest.cells.get(0, 2));
+        assert_eq!(history.pop().unwrap().generation, 1);
+        assert!(history.pop().is_none());
+    }
+
+    #[test]
+    fn undo_and_redo_edits() {
+        let mut history = History::default();
+        history.record(3, vec![(1, 1, 0, 1)]);
+        history.record(3, vec![(2, 2, 1, 0), (2, 3, 0, 1)]);
+        assert_eq!(history.undo(4), None);
+        assert_eq!(history.undo(3), Some(vec![(2, 2, 1), (2, 3, 0)]));
+        assert_eq!(history.redo(3), Some(vec![(2, 2, 0), (2, 3, 1)]));
+        assert_eq!(history.redo(3), None);
+
+        // A new edit or tick makes the undone edits unreachable.
+        history.undo(3);
+        history.record(3, vec![(0, 0, 0, 1)]);
+        assert_eq!(history.redo(3), None);
+
+        // Stepping back before an edit drops it.
+        history.push(2, Rule::conway(), &BitGrid::new(1, 1), &[]);
+        history.pop();
+        assert_eq!(history.undo(3), None);
+        assert_eq!(history.undo(2), None);
+    }
+}
@@ -1,7 +1,26 @@
+mod bitwise;
+mod grid;
+pub mod hashlife;
+mod history;
+pub mod library;
+pub mod pattern;
+pub mod rule;
+mod rng;
+mod stats;
 mod utils;
 
+use grid::BitGrid;
+use hashlife::HashLife;
+use history::History;
+use pattern::Pattern;
+use rng::SplitMix64;
+use rule::Rule;
+use stats::Stats;
 use wasm_bindgen::prelude::*;
-use std::{collections::btree_set::Union, fmt};
+use std::error::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -20,6 +39,8 @@ fn now() -> f64 {
 }
 */
 
+/// Cell states. Generations rules add dying states `2..rule.states()`
+/// above `Alive`.
 #[wasm_bindgen]
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -28,45 +49,191 @@ pub enum Cell {
     Alive = 1,
 }
 
-impl Cell {
-    fn toggle(&mut self){
-        *self = match *self {
-            Cell::Alive => Cell::Dead,
-            Cell::Dead => Cell::Alive,
-        };
-    }
+/// What lies past the edges of the board.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Topology {
+    /// Each edge wraps around to the opposite one.
+    Torus = 0,
+    /// Cells past the edges are always dead.
+    DeadEdge = 1,
+    /// Left and right wrap as on a torus, top and bottom wrap with the
+    /// columns mirrored.
+    KleinBottle = 2,
+    /// The board grows whenever a live cell reaches an edge, up to
+    /// `MAX_GROWN_SIDE`, and past that behaves like `DeadEdge`.
+    Growing = 3,
+}
+
+/// Cells added on a side when a `Growing` board grows.
+const GROW_BY: u32 = 16;
+
+/// Largest width or height a `Growing` board grows to.
+const MAX_GROWN_SIDE: u32 = 4096;
+
+/// Largest width or height of any board, so `width * height` fits in a
+/// `u32` and the cells in memory.
+const MAX_SIDE: u32 = 1 << 14;
+
+/// Furthest the HashLife window may be from the origin, the largest range
+/// of whole numbers an `f64` from JavaScript holds exactly.
+const MAX_VIEWPORT: f64 = (1u64 << 52) as f64;
+
+/// Latest generation `go_to_generation` goes to, the largest whole number an
+/// `f64` from JavaScript holds exactly.
+const MAX_GENERATION: f64 = (1u64 << 53) as f64;
+
+/// Most generations `go_to_generation` and `step_pow2` tick through without
+/// HashLife, so a mistyped target can't hang the page.
+const MAX_TICKS_AHEAD: u64 = 1 << 16;
+
+/// How `Universe::tick` computes a generation of a two state rule.
+/// Generations rules always go cell by cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kernel {
+    /// Count each cell's neighbours one at a time.
+    CellByCell,
+    /// Count the neighbours of 32 cells at once with bitwise arithmetic.
+    Bitwise,
+    /// `Bitwise`, with rows split across threads when built with the
+    /// `parallel` feature.
+    Parallel,
+}
+
+/// The part of the board that stays put when `Universe::resize` changes its
+/// size.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft = 0,
+    TopRight = 1,
+    Center = 2,
+    BottomLeft = 3,
+    BottomRight = 4,
 }
 
 #[wasm_bindgen]
 pub struct Universe {
     width: u32,
     height: u32,
-    cells: Vec<Cell>,
+    /// Live cells, one bit each.
+    cells: BitGrid,
+    /// Dying state of each cell under a Generations rule, `0` for cells
+    /// that aren't dying. Empty for two state rules.
+    ages: Vec<u8>,
+    /// `tick` writes the next generation here and swaps it in, so no
+    /// generation allocates.
+    next_cells: BitGrid,
+    next_ages: Vec<u8>,
+    /// Scratch rows for the edges of the bitwise kernels.
+    halo: bitwise::Halo,
+    rule: Rule,
+    topology: Topology,
+    generation: u64,
+    history: History,
+    stats: Stats,
+    /// When set, generations run on HashLife's unbounded plane and `cells`
+    /// is the window onto it with its top left cell at `view`.
+    hashlife: Option<HashLife>,
+    view: (i64, i64),
+    /// Cells the last tick changed, as `row * width + column`.
+    changes: Vec<u32>,
+    kernel: Kernel,
 }
 
 #[wasm_bindgen]
 impl Universe {
 
+    /// A 64x64 torus with cells alive in a fixed striped pattern.
     pub fn new() -> Universe {
-        utils::set_panic_hook();
-        let width = 64;
-        let height = 64; 
-
-        let cells = (0..width * height)
-            .map(|i| {
-                if i % 2 == 0 || i % 7 == 0 {
-                    Cell::Alive
-                } else {
-                    Cell::Dead
-                }
-            })
-            .collect();
+        let mut universe = Universe::with_size(64, 64);
+        for i in 0..64 * 64 {
+            if i % 2 == 0 || i % 7 == 0 {
+                universe.cells.set(i / 64, i % 64, true);
+            }
+        }
+        universe.changed();
+        universe
+    }
 
-        Universe {
+    /// An empty `width` by `height` torus. Sides are at least 1 and at most
+    /// `MAX_SIDE`.
+    pub fn with_size(width: u32, height: u32) -> Universe {
+        utils::set_panic_hook();
+        let (width, height) = (width.clamp(1, MAX_SIDE), height.clamp(1, MAX_SIDE));
+        let cells = BitGrid::new(width, height);
+        let mut universe = Universe {
             width,
             height,
+            next_cells: cells.clone(),
             cells,
+            ages: Vec::new(),
+            next_ages: Vec::new(),
+            halo: bitwise::Halo::default(),
+            rule: Rule::conway(),
+            topology: Topology::Torus,
+            generation: 0,
+            history: History::default(),
+            stats: Stats::default(),
+            hashlife: None,
+            view: (0, 0),
+            changes: Vec::new(),
+            kernel: if cfg!(feature = "parallel") { Kernel::Parallel } else { Kernel::Bitwise },
+        };
+        universe.record_stats();
+        universe
+    }
+
+    /// A `width` by `height` torus of random soup, see `randomize`.
+    pub fn random(width: u32, height: u32, density: f64, seed: u32) -> Universe {
+        let mut universe = Universe::with_size(width, height);
+        universe.randomize(density, seed);
+        universe
+    }
+
+    /// Replace the board with random soup, each cell alive with probability
+    /// `density`. The same seed, size and density
+    /// always give the same soup. Starts over from generation 0.
+    pub fn randomize(&mut self, density: f64, seed: u32) {
+        let density = density.clamp(0.0, 1.0);
+        let mut rng = SplitMix64::new(seed as u64);
+        self.clear_all();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if rng.next_f64() < density {
+                    self.set_state(row, col, Cell::Alive as u8);
+                }
+            }
         }
+        self.changed();
+    }
+
+    /// Change the size to `width` by `height` (1 to `MAX_SIDE`), keeping the
+    /// cells by `anchor` where they are. Cells that end up off the board
+    /// are lost. Under HashLife this only moves the window.
+    pub fn resize(&mut self, width: u32, height: u32, anchor: Anchor) {
+        let (width, height) = (width.clamp(1, MAX_SIDE), height.clamp(1, MAX_SIDE));
+        let grow_x = width as i64 - self.width as i64;
+        let grow_y = height as i64 - self.height as i64;
+        let (top, left) = match anchor {
+            Anchor::TopLeft => (0, 0),
+            Anchor::TopRight => (0, grow_x),
+            Anchor::Center => (grow_y / 2, grow_x / 2),
+            Anchor::BottomLeft => (grow_y, 0),
+            Anchor::BottomRight => (grow_y, grow_x),
+        };
+        self.history.clear();
+        if self.hashlife.is_some() {
+            self.view = (self.view.0 - top, self.view.1 - left);
+            self.width = width;
+            self.height = height;
+            self.cells = BitGrid::new(width, height);
+            self.next_cells = self.cells.clone();
+            self.refresh_view();
+        } else {
+            self.reshape(width, height, top, left);
+        }
+        self.changed();
     }
 
     pub fn render(&self) -> String {
@@ -81,111 +248,1163 @@ impl Universe {
         self.width
     }
 
+    /// Set the width, 1 to `MAX_SIDE` cells, and clear the board. `resize`
+    /// keeps the cells.
     pub fn set_width(&mut self, width: u32){
-        self.width = width;
-        self.cells = (0..width * self.height).map(|_i|Cell::Dead).collect();
+        self.width = width.clamp(1, MAX_SIDE);
+        self.reset_cells();
     }
 
     pub fn height(&self) -> u32 {
         self.height
     }
 
+    /// Set the height, 1 to `MAX_SIDE` cells, and clear the board. `resize`
+    /// keeps the cells.
     pub fn set_height(&mut self, height: u32){
-        self.height = height;
-        self.cells = (0..height * self.width).map(|_i|Cell::Dead).collect();
+        self.height = height.clamp(1, MAX_SIDE);
+        self.reset_cells();
     }
 
-    pub fn launch_glider(&mut self, x: u32, y: u32){
-       self.toggle_cell(x, y);
-       self.toggle_cell(x+1, y);
-       self.toggle_cell(x+2, y); 
-       self.toggle_cell(x, y+1);
-       self.toggle_cell(x+1, y+2); 
+    /// Stamp a glider heading up and left with its top left at row `x`,
+    /// column `y`. Cells past the edges wrap or are clipped as the topology
+    /// says.
+    pub fn launch_glider(&mut self, x: i32, y: i32){
+       let glider = library::get("glider").unwrap().flipped().rotated(1);
+       self.stamp(&glider, x as i64, y as i64);
     }
 
-    pub fn cells(&self) -> *const Cell {
+    /// Names of the patterns `place_pattern` knows.
+    pub fn pattern_names() -> Vec<String> {
+        library::names().map(String::from).collect()
+    }
+
+    /// Stamp a built in pattern with its top left at `row`, `column`,
+    /// mirrored left to right if `flip` and then turned `rotation` quarter
+    /// turns clockwise. Cells past the edges wrap or are clipped as the
+    /// topology says. The stamp is a single edit for `undo`.
+    pub fn place_pattern(&mut self, name: &str, row: i32, column: i32, rotation: u32, flip: bool) -> Result<(), JsValue> {
+        let pattern = match library::get(name) {
+            Some(pattern) => pattern,
+            None => {
+                let names: Vec<&str> = library::names().collect();
+                return Err(format!("unknown pattern '{}', try one of: {}", name, names.join(", ")).into());
+            }
+        };
+        let pattern = if flip { pattern.flipped() } else { pattern };
+        self.stamp(&pattern.rotated(rotation), row as i64, column as i64);
+        Ok(())
+    }
+
+    /// Live cells as bits: the cell at `row`, `column` is alive when bit
+    /// `column % 8` of byte `row * stride() + column / 8` is set.
+    pub fn cells(&self) -> *const u8 {
         self.cells.as_ptr()
     }
 
-    pub fn toggle_cell(&mut self, row: u32, column: u32) {
-        let idx = self.get_index(row, column);
-        self.cells[idx].toggle();
+    /// Bytes per row of `cells`.
+    pub fn stride(&self) -> usize {
+        self.cells.stride_bytes()
+    }
+
+    /// Under a Generations rule (`states() > 2`), one byte per cell in row
+    /// major order with the dying state, or `0` for live and dead cells.
+    /// Not valid for two state rules.
+    pub fn ages(&self) -> *const u8 {
+        self.ages.as_ptr()
+    }
+
+    /// The rule in B/S notation, e.g. `B3/S23` or `B2/S345/C4`.
+    pub fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Switch to another rule such as `B36/S23` (HighLife), `B2/S` (Seeds)
+    /// or the Generations rule `B2/S345/C4`. Cells in states the new rule
+    /// doesn't have become dead.
+    pub fn set_rule(&mut self, rule: &str) -> Result<(), JsValue> {
+        let rule = rule.parse::<Rule>().map_err(|err| err.to_string())?;
+        self.apply_rule(rule);
+        Ok(())
+    }
+
+    /// Number of cell states in the current rule.
+    pub fn states(&self) -> u8 {
+        self.rule.states()
+    }
+
+    /// Clear the universe and center a pattern file on it. RLE, Life 1.06
+    /// and plaintext `.cells` files are accepted, and an RLE `rule = `
+    /// header switches the rule.
+    pub fn load_pattern(&mut self, raw: &str) -> Result<(), JsValue> {
+        let pattern = Pattern::parse(raw).map_err(|err| err.to_string())?;
+        self.load(&pattern).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// The live cells as RLE, including the rule.
+    pub fn to_rle(&self) -> String {
+        self.pattern().to_rle()
+    }
+
+    /// The live cells as Life 1.06.
+    pub fn to_life106(&self) -> String {
+        self.pattern().to_life106()
+    }
+
+    /// The live cells as a plaintext `.cells` file.
+    pub fn to_plaintext(&self) -> String {
+        self.pattern().to_plaintext()
+    }
+
+    /// Toggle between dead and alive. Dying cells become dead. Past the
+    /// edges the cell wraps as the topology says; returns `false`, changing
+    /// nothing, past a dead edge.
+    pub fn toggle_cell(&mut self, row: i32, column: i32) -> bool {
+        match self.neighbor(row as i64, column as i64) {
+            Some((row, column)) => {
+                let state = self.toggled(row, column);
+                self.edit(&[(row, column, state)]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set one cell to `state`, wrapping like `toggle_cell`. Returns
+    /// `false`, changing nothing, past a dead edge or if the rule has no
+    /// such state.
+    pub fn set_cell(&mut self, row: i32, column: i32, state: u8) -> bool {
+        match self.neighbor(row as i64, column as i64) {
+            Some((row, column)) if state < self.rule.states() => {
+                self.paint(&[(row, column)], state);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Bring the `width` by `height` rectangle with its top left at `row`,
+    /// `column` to life, as one edit. Past the edges it wraps or is clipped
+    /// as the topology says.
+    pub fn fill_rect(&mut self, row: i32, column: i32, width: u32, height: u32) {
+        let cells = self.rect_cells(row as i64, column as i64, width, height);
+        self.paint(&cells, Cell::Alive as u8);
+    }
+
+    /// Kill every cell in a rectangle, as `fill_rect` brings them to life.
+    pub fn clear_rect(&mut self, row: i32, column: i32, width: u32, height: u32) {
+        let cells = self.rect_cells(row as i64, column as i64, width, height);
+        self.paint(&cells, Cell::Dead as u8);
+    }
+
+    /// Bring the cells on the straight line between two cells to life, as
+    /// one edit. Past the edges it wraps or is clipped as the topology says.
+    pub fn draw_line(&mut self, from_row: i32, from_column: i32, to_row: i32, to_column: i32) {
+        let cells = self.line_cells((from_row as i64, from_column as i64), (to_row as i64, to_column as i64));
+        self.paint(&cells, Cell::Alive as u8);
+    }
+
+    /// Kill the cells on a line, as `draw_line` brings them to life.
+    pub fn erase_line(&mut self, from_row: i32, from_column: i32, to_row: i32, to_column: i32) {
+        let cells = self.line_cells((from_row as i64, from_column as i64), (to_row as i64, to_column as i64));
+        self.paint(&cells, Cell::Dead as u8);
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    /// Choose what lies past the edges. HashLife ignores this, its plane
+    /// has no edges.
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.changed();
     }
 
     fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
         let mut count = 0;
-        for delta_row in [self.height - 1, 0, 1].iter().cloned() {
-            for delta_col in [self.width - 1, 0, 1].iter().cloned() {
+        for delta_row in [-1, 0, 1].iter().cloned() {
+            for delta_col in [-1, 0, 1].iter().cloned() {
                 if delta_row == 0 && delta_col == 0 {
                     continue;
                 }
 
-                let neighbor_row = (row + delta_row) % self.height;
-                let neighbor_col = (column + delta_col) % self.width;
-                let idx = self.get_index(neighbor_row, neighbor_col);
-                count += self.cells[idx] as u8;
+                let neighbor = self.neighbor(row as i64 + delta_row, column as i64 + delta_col);
+                if let Some((neighbor_row, neighbor_col)) = neighbor {
+                    count += self.cells.get(neighbor_row, neighbor_col) as u8;
+                }
             }
         }
         count
     }
 
+    /// Write the next generation into `next_cells`, and `next_ages` with
+    /// `generations`, one cell at a time.
+    fn next_cell_by_cell(&mut self, generations: bool) {
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let cell = self.state(row, col);
+                let live_neighbors = self.live_neighbor_count(row, col);
+
+                let next_cell = self.rule.next(cell, live_neighbors);
+                self.next_cells.set(row, col, next_cell == Cell::Alive as u8);
+                if generations {
+                    let idx = self.get_index(row, col);
+                    self.next_ages[idx] = if next_cell > Cell::Alive as u8 { next_cell } else { 0 };
+                }
+            }
+        }
+    }
+
     pub fn tick(&mut self) {
-        let mut next = self.cells.clone();
+        self.generation += 1;
+        self.changes.clear();
+        if let Some(life) = &mut self.hashlife {
+            life.step(0);
+            self.next_cells.clone_from(&self.cells);
+            self.refresh_view();
+            self.cells.diff_into(&self.next_cells, &mut self.changes);
+            self.record_stats();
+            return;
+        }
+        self.history.push(self.generation - 1, self.rule, &self.cells, &self.ages);
+        let size = (self.width, self.height);
+        if self.topology == Topology::Growing {
+            self.grow();
+        }
 
+        let generations = !self.ages.is_empty();
+
+        if !generations && self.kernel != Kernel::CellByCell {
+            let parallel = self.kernel == Kernel::Parallel;
+            let (cells, next, halo) = (&self.cells, &mut self.next_cells, &mut self.halo);
+            bitwise::step(cells, next, halo, &self.rule, self.topology, parallel);
+        } else {
+            self.next_cell_by_cell(generations);
+        }
+
+        if size != (self.width, self.height) {
+            // The board grew, so every cell moved.
+            self.changes.extend(0..self.width * self.height);
+        } else {
+            self.cells.diff_into(&self.next_cells, &mut self.changes);
+            if generations {
+                // Dying cells age without their live bit changing.
+                let width = self.width;
+                let (cells, next_cells) = (&self.cells, &self.next_cells);
+                let aged = self.ages.iter().zip(&self.next_ages).enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(|(i, _)| i as u32)
+                    .filter(|&i| cells.get(i / width, i % width) == next_cells.get(i / width, i % width));
+                self.changes.extend(aged);
+            }
+        }
+
+        std::mem::swap(&mut self.cells, &mut self.next_cells);
+        std::mem::swap(&mut self.ages, &mut self.next_ages);
+        self.record_stats();
+    }
+
+    /// Pointer to the cells the last `tick` changed, `changes_len` `u32`s
+    /// of `row * width + column`, so only those need repainting. Nothing
+    /// else updates the list: redraw everything after any other change.
+    pub fn changes(&self) -> *const u32 {
+        self.changes.as_ptr()
+    }
+
+    pub fn changes_len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Run generations on HashLife instead of cell by cell, starting from
+    /// the current cells. The board becomes a window onto an unbounded
+    /// plane, moved with `set_viewport`. Only two state rules without `B0`
+    /// can run on HashLife.
+    pub fn use_hashlife(&mut self) -> Result<(), JsValue> {
+        if self.hashlife.is_some() {
+            return Ok(());
+        }
+        let mut life = HashLife::new(self.rule).map_err(|err| err.to_string())?;
         for row in 0..self.height {
             for col in 0..self.width {
-                let idx = self.get_index(row, col);
-                let cell = self.cells[idx];
-                let live_neighbors = self.live_neighbor_count(row, col);
+                if self.cells.get(row, col) {
+                    life.set(row as i64, col as i64, true);
+                }
+            }
+        }
+        self.hashlife = Some(life);
+        self.view = (0, 0);
+        self.history.clear();
+        self.changed();
+        Ok(())
+    }
+
+    /// Go back to ticking cell by cell within the board's topology, keeping
+    /// the cells currently in view. Switching engines clears the history.
+    pub fn use_naive(&mut self) {
+        self.hashlife = None;
+        self.view = (0, 0);
+        self.history.clear();
+        self.changed();
+    }
+
+    pub fn is_hashlife(&self) -> bool {
+        self.hashlife.is_some()
+    }
+
+    /// Advance `2^k` generations at once. HashLife takes these in a single
+    /// step, the naive engine ticks `2^k` times. `k` is at most 48, larger
+    /// values are taken as 48. Returns `false`, without moving, if the naive
+    /// engine would tick more than `MAX_TICKS_AHEAD` times.
+    pub fn step_pow2(&mut self, k: u8) -> bool {
+        let k = k.min(hashlife::MAX_STEP);
+        match &mut self.hashlife {
+            Some(life) => {
+                life.step(k);
+                self.generation = self.generation.saturating_add(1 << k);
+                self.refresh_view();
+                self.record_stats();
+            }
+            None if 1u64 << k > MAX_TICKS_AHEAD => return false,
+            None => (0..1u64 << k).for_each(|_| self.tick()),
+        }
+        true
+    }
+
+    /// Under HashLife, show the plane from row `top` and column `left`.
+    /// Coordinates are whole numbers and may be negative, and are clamped
+    /// to within `2^52` of the origin. Ignored unless both are finite.
+    pub fn set_viewport(&mut self, top: f64, left: f64) {
+        if self.hashlife.is_some() && top.is_finite() && left.is_finite() {
+            let clamp = |v: f64| v.clamp(-MAX_VIEWPORT, MAX_VIEWPORT) as i64;
+            self.view = (clamp(top), clamp(left));
+            // Edits were recorded relative to the old window.
+            self.history.clear();
+            self.refresh_view();
+            self.changed();
+        }
+    }
+
+    pub fn viewport_top(&self) -> f64 {
+        self.view.0 as f64
+    }
+
+    pub fn viewport_left(&self) -> f64 {
+        self.view.1 as f64
+    }
+
+    /// Generations ticked since the universe was created or last loaded.
+    pub fn generation(&self) -> f64 {
+        self.generation as f64
+    }
+
+    /// Go back one generation. Returns `false` when there's no history
+    /// left, and always under HashLife, which keeps none.
+    pub fn step_back(&mut self) -> bool {
+        if self.hashlife.is_some() {
+            return false;
+        }
+        let mut snapshot = match self.history.pop() {
+            Some(snapshot) => snapshot,
+            None => return false,
+        };
+        self.width = snapshot.cells.width();
+        self.height = snapshot.cells.height();
+        std::mem::swap(&mut self.cells, &mut snapshot.cells);
+        std::mem::swap(&mut self.ages, &mut snapshot.ages);
+        if self.next_cells.width() != self.width || self.next_cells.height() != self.height {
+            self.next_cells = self.cells.clone();
+        }
+        self.next_ages.resize(self.ages.len(), 0);
+        self.rule = snapshot.rule;
+        self.generation = snapshot.generation;
+        self.record_stats();
+        true
+    }
+
+    /// Tick forward or step back to `generation`. Returns `false`, after
+    /// going as far back as it can, if history doesn't reach that far.
+    /// Also returns `false`, without moving, for targets that aren't finite,
+    /// are past `2^53`, or are more than `MAX_TICKS_AHEAD` generations ahead
+    /// of the cell by cell engines.
+    pub fn go_to_generation(&mut self, generation: f64) -> bool {
+        if !generation.is_finite() || generation > MAX_GENERATION {
+            return false;
+        }
+        let target = generation.max(0.0) as u64;
+        if self.hashlife.is_none() && target > self.generation.saturating_add(MAX_TICKS_AHEAD) {
+            return false;
+        }
+        while self.generation > target {
+            if !self.step_back() {
+                return false;
+            }
+        }
+        match &mut self.hashlife {
+            Some(life) => {
+                life.advance(target - self.generation);
+                self.generation = target;
+                self.refresh_view();
+                self.record_stats();
+            }
+            None => {
+                while self.generation < target {
+                    self.tick();
+                }
+            }
+        }
+        true
+    }
+
+    /// Number of generations `step_back` can go back.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn history_limit(&self) -> usize {
+        self.history.limit()
+    }
+
+    /// Keep at most `limit` generations to step back through.
+    pub fn set_history_limit(&mut self, limit: usize) {
+        self.history.set_limit(limit);
+    }
+
+    /// Undo the last edit, such as `toggle_cell` or `fill_rect`, made in
+    /// this generation. Returns `false` if there's none.
+    pub fn undo(&mut self) -> bool {
+        match self.history.undo(self.generation) {
+            Some(cells) => {
+                cells.into_iter().for_each(|(row, col, state)| self.set_state(row, col, state));
+                self.changed();
+                true
+            }
+            None => false,
+        }
+    }
 
-                let next_cell = match (cell, live_neighbors) {
-                    // Rule 1: Any live cell with fewer than two live neighbours
-                    // dies, as if caused by underpopulation.
-                    (Cell::Alive, x) if x < 2 => Cell::Dead,
-                    // Rule 2: Any live cell with two or three live neighbours
-                    // lives on to the next generation.
-                    (Cell::Alive, 2) | (Cell::Alive, 3) => Cell::Alive,
-                    // Rule 3: Any live cell with more than three live
-                    // neighbours dies, as if by overpopulation.
-                    (Cell::Alive, x) if x > 3 => Cell::Dead,
-                    // Rule 4: Any dead cell with exactly three live neighbours
-                    // becomes a live cell, as if by reproduction.
-                    (Cell::Dead, 3) => Cell::Alive,
-                    // All other cells remain in the same state.
-                    (otherwise, _) => otherwise,
-                };
-                next[idx] = next_cell;
+    /// Redo the last undone edit. Returns `false` if there's none.
+    pub fn redo(&mut self) -> bool {
+        match self.history.redo(self.generation) {
+            Some(cells) => {
+                cells.into_iter().for_each(|(row, col, state)| self.set_state(row, col, state));
+                self.changed();
+                true
             }
+            None => false,
         }
+    }
+
+    /// Live cells in the current generation, across the whole plane under
+    /// HashLife.
+    pub fn population(&self) -> f64 {
+        self.stats.population() as f64
+    }
+
+    /// Population of each generation since `populations_start`, for the
+    /// last few thousand generations.
+    pub fn populations(&self) -> Vec<f64> {
+        self.stats.populations().map(|p| p as f64).collect()
+    }
+
+    /// Generation of the first entry in `populations`.
+    pub fn populations_start(&self) -> f64 {
+        self.stats.first_generation() as f64
+    }
+
+    /// Generation the board settled into a still life or oscillator, if it
+    /// has since the last edit. Not detected under HashLife.
+    pub fn cycle_start(&self) -> Option<f64> {
+        self.stats.cycle().map(|c| c.start as f64)
+    }
 
-        self.cells = next;
+    /// Period of the cycle, 1 for a still life.
+    pub fn cycle_period(&self) -> Option<f64> {
+        self.stats.cycle().map(|c| c.period as f64)
+    }
+
+    /// The cycle in words, e.g. `period-2 oscillator reached at gen 412`.
+    pub fn cycle_report(&self) -> Option<String> {
+        self.stats.report()
     }
 
 }
 
 impl Universe{
-    pub fn get_cells(&self) -> &[Cell]{
-        &self.cells
+    pub fn kernel(&self) -> Kernel {
+        self.kernel
+    }
+
+    pub fn set_kernel(&mut self, kernel: Kernel) {
+        self.kernel = kernel;
+    }
+
+    /// A copy of every cell's state, one byte per cell in row major order.
+    pub fn get_cells(&self) -> Vec<u8>{
+        (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (row, col)))
+            .map(|(row, col)| self.state(row, col))
+            .collect()
     }
 
+    /// Bring `cells` to life, wrapping or skipping those past the edges as
+    /// the topology says.
     pub fn set_cells(&mut self, cells: &[(u32, u32)]){
         for (row, col) in cells.iter().cloned() {
-            let idx = self.get_index(row, col);
-            self.cells[idx] = Cell::Alive;
+            if let Some((row, col)) = self.neighbor(row as i64, col as i64) {
+                self.set_state(row, col, Cell::Alive as u8);
+            }
+        }
+    }
+
+    /// State of one cell, see `Cell`.
+    pub fn state(&self, row: u32, column: u32) -> u8 {
+        if self.cells.get(row, column) {
+            Cell::Alive as u8
+        } else if self.ages.is_empty() {
+            Cell::Dead as u8
+        } else {
+            self.ages[self.get_index(row, column)]
+        }
+    }
+
+    /// The state `toggle_cell` gives a cell.
+    fn toggled(&self, row: u32, column: u32) -> u8 {
+        if self.state(row, column) == Cell::Dead as u8 {
+            Cell::Alive as u8
+        } else {
+            Cell::Dead as u8
+        }
+    }
+
+    /// Set cells to new states as one undoable edit.
+    fn edit(&mut self, cells: &[(u32, u32, u8)]) {
+        let changes = cells.iter().map(|&(row, col, state)| (row, col, self.state(row, col), state)).collect();
+        for &(row, col, state) in cells {
+            self.set_state(row, col, state);
+        }
+        self.history.record(self.generation, changes);
+        self.changed();
+    }
+
+    /// Set `cells` to `state` as one edit, leaving out those already in it.
+    fn paint(&mut self, cells: &[(u32, u32)], state: u8) {
+        let cells: Vec<_> = cells
+            .iter()
+            .filter(|&&(row, col)| self.state(row, col) != state)
+            .map(|&(row, col)| (row, col, state))
+            .collect();
+        if !cells.is_empty() {
+            self.edit(&cells);
+        }
+    }
+
+    /// The board cells of a rectangle, wrapped or clipped by the topology.
+    /// Wrapped, a rectangle bigger than the board covers all of it.
+    fn rect_cells(&self, row: i64, column: i64, width: u32, height: u32) -> Vec<(u32, u32)> {
+        let wraps = matches!(self.topology, Topology::Torus | Topology::KleinBottle);
+        let span = |start: i64, len: u32, side: u32| {
+            if wraps {
+                start..start + len.min(side) as i64
+            } else {
+                start.max(0)..(start + len as i64).min(side as i64)
+            }
+        };
+        let rows = span(row, height, self.height);
+        let columns = span(column, width, self.width);
+        let mut cells: Vec<_> = rows
+            .flat_map(|r| columns.clone().map(move |c| (r, c)))
+            .filter_map(|(r, c)| self.neighbor(r, c))
+            .collect();
+        cells.sort_unstable();
+        cells.dedup();
+        cells
+    }
+
+    /// The board cells nearest a straight line, one for each row or column
+    /// it crosses, whichever is more, wrapped or clipped by the topology.
+    /// Wrapped, a line stops after `width * height` cells, however long.
+    fn line_cells(&self, from: (i64, i64), to: (i64, i64)) -> Vec<(u32, u32)> {
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs());
+        // `start + (end - start) * step / steps`, to the nearest cell. Wide
+        // enough for lines between any two `i32` cells.
+        let along = |start: i64, end: i64, step: i64| {
+            let (start, end, step, steps) = (start as i128, end as i128, step as i128, steps as i128);
+            (start + (2 * (end - start) * step + steps).div_euclid(2 * steps.max(1))) as i64
+        };
+        let point = |step| self.neighbor(along(from.0, to.0, step), along(from.1, to.1, step));
+        if matches!(self.topology, Topology::Torus | Topology::KleinBottle) {
+            let cells = self.width as usize * self.height as usize;
+            return (0..=steps).take(cells).filter_map(point).collect();
+        }
+
+        // Each coordinate only moves one way, so the steps on the board are
+        // one run, found by bisection: the first step in `0..=steps` that
+        // is `past`, which stays true from there on.
+        let first = |past: &dyn Fn(i64) -> bool| {
+            let (mut low, mut high) = (0, steps + 1);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                if past(mid) { high = mid } else { low = mid + 1 }
+            }
+            low
+        };
+        let on_board = |start: i64, end: i64, side: u32| {
+            let (at, side) = (|step| along(start, end, step), side as i64);
+            if start <= end {
+                first(&|step| at(step) >= 0)..first(&|step| at(step) >= side)
+            } else {
+                first(&|step| at(step) < side)..first(&|step| at(step) < 0)
+            }
+        };
+        let rows = on_board(from.0, to.0, self.height);
+        let columns = on_board(from.1, to.1, self.width);
+        (rows.start.max(columns.start)..rows.end.min(columns.end)).filter_map(point).collect()
+    }
+
+    /// Start the statistics over from the current cells.
+    fn changed(&mut self) {
+        self.stats.reset();
+        self.record_stats();
+    }
+
+    fn record_stats(&mut self) {
+        let (population, hash) = match &self.hashlife {
+            // HashLife's plane is too big to hash every generation.
+            Some(life) => (life.population(), None),
+            None => {
+                let mut hasher = DefaultHasher::new();
+                self.cells.hash(&mut hasher);
+                self.ages.hash(&mut hasher);
+                (self.cells.count() as u64, Some(hasher.finish()))
+            }
+        };
+        self.stats.record(self.generation, population, hash);
+    }
+
+    fn set_state(&mut self, row: u32, column: u32, state: u8) {
+        self.cells.set(row, column, state == Cell::Alive as u8);
+        if let Some(life) = &mut self.hashlife {
+            let (top, left) = self.view;
+            life.set(top + row as i64, left + column as i64, state == Cell::Alive as u8);
+        }
+        if !self.ages.is_empty() {
+            let idx = self.get_index(row, column);
+            self.ages[idx] = if state > Cell::Alive as u8 { state } else { 0 };
+        }
+    }
+
+    /// The cell at `row`, `column` once the topology has brought it onto the
+    /// board, or `None` past a dead edge.
+    fn neighbor(&self, row: i64, column: i64) -> Option<(u32, u32)> {
+        let (height, width) = (self.height as i64, self.width as i64);
+        let on_board = (0..height).contains(&row) && (0..width).contains(&column);
+        match self.topology {
+            Topology::Torus => Some((row.rem_euclid(height) as u32, column.rem_euclid(width) as u32)),
+            Topology::KleinBottle => {
+                let column = if (0..height).contains(&row) { column } else { width - 1 - column };
+                Some((row.rem_euclid(height) as u32, column.rem_euclid(width) as u32))
+            }
+            Topology::DeadEdge | Topology::Growing if on_board => Some((row as u32, column as u32)),
+            Topology::DeadEdge | Topology::Growing => None,
+        }
+    }
+
+    /// Grow the board on each side a live cell has reached, so the next
+    /// generation has room.
+    fn grow(&mut self) {
+        let (height, width) = (self.height, self.width);
+        let live_in_row = |row| (0..width).any(|col| self.cells.get(row, col));
+        let live_in_col = |col| (0..height).any(|row| self.cells.get(row, col));
+        let room = |side: u32| if side + GROW_BY <= MAX_GROWN_SIDE { GROW_BY } else { 0 };
+
+        let top = if live_in_row(0) { room(height) } else { 0 };
+        let bottom = if live_in_row(height - 1) { room(height + top) } else { 0 };
+        let left = if live_in_col(0) { room(width) } else { 0 };
+        let right = if live_in_col(width - 1) { room(width + left) } else { 0 };
+        if top + bottom + left + right > 0 {
+            self.reshape(width + left + right, height + top + bottom, top as i64, left as i64);
+        }
+    }
+
+    /// Resize to `width` by `height`, moving each cell down `top` rows and
+    /// right `left` columns. Cells that end up off the board are lost.
+    fn reshape(&mut self, width: u32, height: u32, top: i64, left: i64) {
+        let old = self.get_cells();
+        let old_width = self.width as usize;
+        self.width = width;
+        self.height = height;
+        self.cells = BitGrid::new(width, height);
+        self.next_cells = self.cells.clone();
+        if self.rule.states() > 2 {
+            self.ages = vec![0; (width * height) as usize];
+            self.next_ages = self.ages.clone();
+        }
+        for (i, &state) in old.iter().enumerate().filter(|(_, &s)| s != 0) {
+            let row = (i / old_width) as i64 + top;
+            let col = (i % old_width) as i64 + left;
+            if (0..height as i64).contains(&row) && (0..width as i64).contains(&col) {
+                self.set_state(row as u32, col as u32, state);
+            }
+        }
+    }
+
+    /// Switch rules, sizing the ages buffers for its states. Rules HashLife
+    /// can't run switch back to the naive engine.
+    fn apply_rule(&mut self, rule: Rule) {
+        if let Some(life) = &mut self.hashlife {
+            if life.set_rule(rule).is_err() {
+                self.use_naive();
+            }
+        }
+        let cells = (self.width * self.height) as usize;
+        if rule.states() > 2 {
+            let states = rule.states();
+            self.ages.resize(cells, 0);
+            for age in self.ages.iter_mut().filter(|a| **a >= states) {
+                *age = Cell::Dead as u8;
+            }
+            self.next_ages.resize(cells, 0);
+        } else {
+            self.ages = Vec::new();
+            self.next_ages = Vec::new();
+        }
+        self.rule = rule;
+        self.changed();
+    }
+
+    /// Reallocate every buffer, all dead, for the current size.
+    fn reset_cells(&mut self) {
+        self.history.clear();
+        self.cells = BitGrid::new(self.width, self.height);
+        self.next_cells = self.cells.clone();
+        if self.rule.states() > 2 {
+            self.ages = vec![0; (self.width * self.height) as usize];
+            self.next_ages = self.ages.clone();
         }
+        self.refresh_view();
+        self.changed();
+    }
+
+    /// Copy HashLife's cells in view into `cells`.
+    fn refresh_view(&mut self) {
+        if let Some(life) = &self.hashlife {
+            let cells = &mut self.cells;
+            cells.clear();
+            let (top, left) = self.view;
+            life.for_each_live(top, left, self.height, self.width, &mut |row, col| {
+                cells.set(row, col, true)
+            });
+        }
+    }
+
+    /// Bring `pattern`'s live cells to life with its top left at `row`,
+    /// `column`, as one edit. Cells past the edges wrap or are clipped as
+    /// the topology says.
+    pub fn stamp(&mut self, pattern: &Pattern, row: i64, column: i64) {
+        let cells: Vec<_> = pattern
+            .cells()
+            .filter_map(|(r, c, _)| self.neighbor(row + r as i64, column + c as i64))
+            .map(|(r, c)| (r, c, Cell::Alive as u8))
+            .collect();
+        self.edit(&cells);
+    }
+
+    /// Bounding box of the non-dead cells, with the current rule.
+    pub fn pattern(&self) -> Pattern {
+        let mut pattern = Pattern::new(self.width, self.height, self.get_cells()).trimmed();
+        pattern.rule = Some(self.rule);
+        pattern
+    }
+
+    /// Copy `pattern` onto the universe with its top left at `row`,
+    /// `column`. Dead cells in the pattern overwrite live ones.
+    pub fn paste(&mut self, pattern: &Pattern, row: u32, column: u32) -> Result<(), Box<dyn Error>> {
+        let fits = |start: u32, len: u32, side: u32| start.checked_add(len).is_some_and(|end| end <= side);
+        if !fits(row, pattern.height(), self.height) || !fits(column, pattern.width(), self.width) {
+            return Err(format!(
+                "pattern {}x{} at row {} column {} doesn't fit a {}x{} universe",
+                pattern.width(), pattern.height(), row, column, self.width, self.height
+            ).into());
+        }
+        self.check_states(pattern)?;
+        for r in 0..pattern.height() {
+            for c in 0..pattern.width() {
+                self.set_state(row + r, column + c, pattern.state(r, c));
+            }
+        }
+        self.changed();
+        Ok(())
+    }
+
+    /// Clear the universe and center `pattern` on it, switching to the
+    /// pattern's rule if it has one.
+    pub fn load(&mut self, pattern: &Pattern) -> Result<(), Box<dyn Error>> {
+        let pattern = pattern.trimmed();
+        if pattern.width() > self.width || pattern.height() > self.height {
+            return Err(format!(
+                "pattern {}x{} doesn't fit a {}x{} universe",
+                pattern.width(), pattern.height(), self.width, self.height
+            ).into());
+        }
+        self.check_states(&pattern)?;
+        if let Some(rule) = pattern.rule {
+            self.apply_rule(rule);
+        }
+        self.clear_all();
+        let row = (self.height - pattern.height()) / 2;
+        let column = (self.width - pattern.width()) / 2;
+        self.paste(&pattern, row, column)
+    }
+
+    /// Kill every cell and start over from generation 0 with no history.
+    fn clear_all(&mut self) {
+        self.cells.clear();
+        self.ages.iter_mut().for_each(|a| *a = 0);
+        if let Some(life) = &mut self.hashlife {
+            life.clear();
+            self.view = (0, 0);
+        }
+        self.generation = 0;
+        self.history.clear();
+    }
+
+    fn check_states(&self, pattern: &Pattern) -> Result<(), Box<dyn Error>> {
+        let rule = pattern.rule.unwrap_or(self.rule);
+        match pattern.cells().find(|&(_, _, state)| state >= rule.states()) {
+            Some((_, _, state)) => Err(format!("pattern cell state {} isn't valid under {}", state, rule).into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Default for Universe {
+    fn default() -> Universe {
+        Universe::new()
     }
 }
 
 impl fmt::Display for Universe{
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result{
-        for line in self.cells.as_slice().chunks(self.width as usize) {
+        for line in self.get_cells().chunks(self.width as usize) {
             for &cell in line {
-                let symbol = if cell == Cell::Dead { '◻' } else { '◼' };
+                let symbol = if cell == Cell::Dead as u8 { '◻' } else { '◼' };
                 write!(f, "{}", symbol)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn generations_tick_ages_cells() {
+        let mut universe = Universe::new();
+        universe.set_width(5);
+        universe.set_height(5);
+        universe.apply_rule("B2/S/C3".parse().unwrap());
+        universe.set_cells(&[(2, 1), (2, 2)]);
+
+        // Brian's Brain: the pair dies into state 2 and births above and below.
+        universe.tick();
+        assert_eq!(universe.state(2, 1), 2);
+        assert_eq!(universe.state(1, 1), 1);
+        assert_eq!(universe.state(3, 2), 1);
+        assert_eq!(universe.get_cells().iter().filter(|&&s| s == 1).count(), 4);
+
+        universe.tick();
+        assert_eq!(universe.state(2, 1), 0);
+        assert_eq!(universe.state(1, 1), 2);
+
+        // Back to two states drops the ages buffers.
+        universe.apply_rule(Rule::conway());
+        assert!(universe.ages.is_empty() && universe.next_ages.is_empty());
+        assert!(universe.get_cells().iter().all(|&s| s < 2));
+    }
+
+    #[test]
+    fn hashlife_window_follows_the_plane() {
+        let mut universe = Universe::new();
+        universe.set_width(8);
+        universe.set_height(8);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        let glider = universe.get_cells();
+        universe.use_hashlife().unwrap();
+
+        // Off the edge of the board instead of wrapping around it.
+        assert!(universe.step_pow2(5));
+        assert!(universe.get_cells().iter().all(|&s| s == 0));
+        universe.set_viewport(8.0, 8.0);
+        assert_eq!(universe.get_cells(), glider);
+
+        // Edits land on the plane where the window is.
+        universe.toggle_cell(0, 1);
+        universe.set_viewport(0.0, 0.0);
+        universe.set_viewport(8.0, 8.0);
+        assert_eq!(universe.get_cells().iter().filter(|&&s| s == 1).count(), 4);
+
+        // Generations rules fall back to the naive engine.
+        universe.apply_rule("B2/S/C3".parse().unwrap());
+        assert!(!universe.is_hashlife());
+        assert_eq!(universe.get_cells().iter().filter(|&&s| s == 1).count(), 4);
+
+        // Far off windows and huge steps are clamped, not overflowed.
+        universe.apply_rule(Rule::conway());
+        universe.use_hashlife().unwrap();
+        universe.set_viewport(1e300, -1e300);
+        let view = (universe.viewport_top(), universe.viewport_left());
+        assert_eq!(view, (MAX_VIEWPORT, -MAX_VIEWPORT));
+        universe.set_viewport(f64::NAN, 0.0);
+        assert_eq!(universe.viewport_top(), MAX_VIEWPORT);
+        assert!(universe.step_pow2(64));
+        assert_eq!(universe.generation(), (32 + (1u64 << 48)) as f64);
+
+        // The naive engine only ticks up to MAX_TICKS_AHEAD at a time.
+        universe.use_naive();
+        let generation = universe.generation();
+        assert!(!universe.step_pow2(17));
+        assert!(!universe.step_pow2(64));
+        assert_eq!(universe.generation(), generation);
+        assert!(universe.step_pow2(4));
+        assert_eq!(universe.generation(), generation + 16.0);
+    }
+
+    fn glider(topology: Topology) -> Universe {
+        let mut universe = Universe::new();
+        universe.set_width(6);
+        universe.set_height(6);
+        universe.set_topology(topology);
+        universe.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        universe
+    }
+
+    fn population(universe: &Universe) -> usize {
+        universe.get_cells().iter().filter(|&&s| s == 1).count()
+    }
+
+    #[test]
+    fn topologies() {
+        // A glider on a torus comes back where it started.
+        let mut torus = glider(Topology::Torus);
+        let start = torus.get_cells();
+        (0..24).for_each(|_| torus.tick());
+        assert_eq!(torus.get_cells(), start);
+
+        // Against a dead corner it settles into a block.
+        let mut dead = glider(Topology::DeadEdge);
+        (0..24).for_each(|_| dead.tick());
+        assert_eq!(population(&dead), 4);
+        assert!(dead.state(4, 4) == 1 && dead.state(5, 5) == 1);
+
+        // Crossing the top or bottom edge of a Klein bottle mirrors columns.
+        let klein = glider(Topology::KleinBottle);
+        assert_eq!(klein.neighbor(-1, 1), Some((5, 4)));
+        assert_eq!(klein.neighbor(6, 0), Some((0, 5)));
+        assert_eq!(klein.neighbor(2, -1), Some((2, 5)));
+        assert_eq!(klein.neighbor(-1, -1), Some((5, 0)));
+
+        // A growing board makes room and the glider keeps going.
+        let mut growing = glider(Topology::Growing);
+        (0..40).for_each(|_| growing.tick());
+        assert_eq!(population(&growing), 5);
+        assert_eq!(growing.width(), 6 + GROW_BY * 2);
+        assert_eq!(growing.height(), 6 + GROW_BY * 2);
+        assert_eq!(growing.pattern(), glider(Topology::Torus).pattern());
+    }
+
+    #[test]
+    fn rewind_and_undo() {
+        let mut universe = glider(Topology::Torus);
+        let start = universe.get_cells();
+        (0..3).for_each(|_| universe.tick());
+        let third = universe.get_cells();
+
+        // Edits undo and redo within a generation.
+        universe.launch_glider(0, 0);
+        universe.toggle_cell(5, 5);
+        assert!(universe.undo());
+        assert_eq!(universe.state(5, 5), 0);
+        assert!(universe.undo());
+        assert_eq!(universe.get_cells(), third);
+        assert!(!universe.undo());
+        assert!(universe.redo());
+        assert_ne!(universe.get_cells(), third);
+        assert!(universe.undo());
+
+        universe.tick();
+        assert!(universe.step_back());
+        assert_eq!(universe.generation(), 3.0);
+        assert_eq!(universe.get_cells(), third);
+
+        assert!(universe.go_to_generation(10.0));
+        assert!(universe.go_to_generation(0.0));
+        assert_eq!(universe.get_cells(), start);
+        assert!(!universe.step_back());
+
+        // Only as far back as the history limit.
+        universe.set_history_limit(2);
+        assert!(universe.go_to_generation(5.0));
+        assert!(!universe.go_to_generation(1.0));
+        assert_eq!(universe.generation(), 3.0);
+
+        // Targets that aren't numbers or are too far ahead go nowhere.
+        for &target in &[f64::NAN, f64::INFINITY, 1e20, 1e6] {
+            assert!(!universe.go_to_generation(target), "{}", target);
+            assert_eq!(universe.generation(), 3.0);
+        }
+        universe.use_hashlife().unwrap();
+        assert!(universe.go_to_generation(1e15));
+        assert_eq!(universe.generation(), 1e15);
+        assert_eq!(universe.population(), 5.0);
+        assert!(!universe.go_to_generation(1e20));
+    }
+
+    #[test]
+    fn detects_oscillators_and_still_lifes() {
+        let mut universe = Universe::new();
+        universe.set_width(8);
+        universe.set_height(8);
+        universe.set_topology(Topology::DeadEdge);
+        // A blinker, and an L tromino that becomes a block.
+        universe.set_cells(&[(1, 1), (1, 2), (1, 3)]);
+        universe.toggle_cell(5, 5);
+        universe.toggle_cell(5, 6);
+        universe.toggle_cell(6, 5);
+        assert_eq!(universe.population(), 6.0);
+
+        (0..4).for_each(|_| universe.tick());
+        assert_eq!(universe.cycle_start(), Some(1.0));
+        assert_eq!(universe.cycle_period(), Some(2.0));
+        assert_eq!(
+            universe.cycle_report().unwrap(),
+            "period-2 oscillator reached at gen 1"
+        );
+        assert_eq!(universe.populations(), vec![6.0, 7.0, 7.0, 7.0, 7.0]);
+
+        // Editing starts detection over.
+        universe.toggle_cell(1, 2);
+        assert_eq!(universe.cycle_period(), None);
+        (0..3).for_each(|_| universe.tick());
+        assert_eq!(
+            universe.cycle_report().unwrap(),
+            "still life reached at gen 5"
+        );
+        assert_eq!(universe.populations_start(), 4.0);
+    }
+
+    #[test]
+    fn launch_glider_wraps_or_clips() {
+        let mut universe = glider(Topology::Torus);
+        universe.set_width(6);
+        universe.launch_glider(1, 1);
+        let mut expected = glider(Topology::Torus);
+        expected.set_width(6);
+        expected.set_cells(&[(1, 1), (2, 1), (3, 1), (1, 2), (2, 3)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+
+        // Right at the corner the glider wraps around a torus.
+        universe.set_width(6);
+        universe.launch_glider(5, 5);
+        expected.set_width(6);
+        expected.set_cells(&[(5, 5), (0, 5), (1, 5), (5, 0), (0, 1)]);
+        assert_eq!(universe.get_cells(), expected.get_cells());
+
+        // A dead edge cuts it off.
+        universe.set_width(6);
+        universe.set_topology(Topology::DeadEdge);
+        universe.launch_glider(5, 5);
+        assert_eq!(population(&universe), 1);
+        assert!(universe.undo());
+        assert_eq!(population(&universe), 0);
+
+        // Only the pulsar's bottom right quarter lands on the board.
+        universe.place_pattern("pulsar", -6, -6, 1, true).unwrap();
+        assert_eq!(population(&universe), 6);
+    }
+
+    #[test]
+    fn random_soup_and_anchored_resize() {
+        let soup = Universe::random(32, 16, 0.3, 7);
+        assert_eq!(soup.get_cells(), Universe::random(32, 16, 0.3, 7).get_cells());
+        assert_ne!(soup.get_cells(), Universe::random(32, 16, 0.3, 8).get_cells());
+        let live = soup.population();
+        assert!(live > 100.0 && live < 200.0, "{} live of 512", live);
+        assert_eq!(Universe::random(8, 8, 1.5, 1).population(), 64.0);
+        assert_eq!(Universe::random(8, 8, -1.0, 1).population(), 0.0);
+
+        let mut universe = Universe::with_size(4, 4);
+        assert_eq!(population(&universe), 0);
+        universe.set_cells(&[(0, 0), (3, 3)]);
+        universe.resize(6, 6, Anchor::BottomRight);
+        assert_eq!((universe.state(2, 2), universe.state(5, 5)), (1, 1));
+        universe.resize(4, 4, Anchor::Center);
+        assert_eq!(universe.state(1, 1), 1);
+        assert_eq!(population(&universe), 1);
+        universe.resize(2, 3, Anchor::TopLeft);
+        assert_eq!((universe.width(), universe.height()), (2, 3));
+        assert_eq!(universe.get_cells(), [0, 0, 0, 1, 0, 0]);
+        universe.resize(5, 3, Anchor::TopRight);
+        assert_eq!(universe.state(1, 4), 1);
+
+        // Under HashLife the window moves over the plane, so cells cut off
+        // come back when it grows again.
+        universe.use_hashlife().unwrap();
+        universe.resize(1, 1, Anchor::BottomLeft);
+        assert_eq!(population(&universe), 0);
+        universe.resize(5, 3, Anchor::BottomLeft);
+        assert_eq!(universe.state(1, 4), 1);
+    }
+
+    #[test]
+    fn tick_lists_changed_cells() {
+        let changes = |universe: &Universe| {
+            let changes = unsafe {
+                std::slice::from_raw_parts(universe.changes(), universe.changes_len())
+            };
+            let mut changes = changes.to_vec();
+            changes.sort_unstable();
+            changes
+        };
+        let mut universe = Universe::with_size(5, 5);
+        universe.set_cells(&[(2, 1), (2, 2), (2, 3)]);
+        universe.tick();
+        assert_eq!(changes(&universe), [7, 11, 13, 17]);
+
+        // Brian's Brain: the blinker starts dying, then its middle cell
+        // goes from dying to dead, which only shows in the ages.
+        universe.apply_rule("B2/S/C3".parse().unwrap());
+        universe.tick();
+        let dying: Vec<_> = (0..25).filter(|&i| universe.state(i / 5, i % 5) == 2).collect();
+        assert_eq!(dying, [7, 12, 17]);
+        universe.tick();
+        assert!(changes(&universe).contains(&12));
+        let before = universe.get_cells();
+        universe.tick();
+        let after = universe.get_cells();
+        let expected: Vec<u32> = (0..25).filter(|&i| before[i as usize] != after[i as usize]).collect();
+        assert_eq!(changes(&universe), expected);
+
+        // A growing board lists every cell after it grows.
+        let mut growing = glider(Topology::Growing);
+        while growing.width() == 6 {
+            growing.tick();
+        }
+        let cells = (growing.width() * growing.height()) as usize;
+        assert_eq!(changes(&growing).len(), cells);
+        growing.tick();
+        assert!(changes(&growing).len() < 10);
+
+        let mut plane = glider(Topology::Torus);
+        plane.use_hashlife().unwrap();
+        let before = plane.get_cells();
+        plane.tick();
+        let after = plane.get_cells();
+        let expected: Vec<u32> = (0..36).filter(|&i| before[i as usize] != after[i as usize]).collect();
+        assert_eq!(changes(&plane), expected);
+    }
+}
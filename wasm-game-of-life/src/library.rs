@@ -0,0 +1,107 @@
+use crate::pattern::Pattern;
+
+/// Built in patterns by name, as RLE.
+const PATTERNS: &[(&str, &str)] = &[
+    // Still lifes.
+    ("block", "x = 2, y = 2\n2o$2o!"),
+    ("beehive", "x = 4, y = 3\nb2ob$o2bo$b2o!"),
+    ("loaf", "x = 4, y = 4\nb2ob$o2bo$bobo$2bo!"),
+    ("boat", "x = 3, y = 3\n2ob$obo$bo!"),
+    // Oscillators.
+    ("blinker", "x = 3, y = 1\n3o!"),
+    ("toad", "x = 4, y = 2\nb3o$3o!"),
+    ("beacon", "x = 4, y = 4\n2o2b$o3b$3bo$2b2o!"),
+    (
+        "pulsar",
+        "x = 13, y = 13\n2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$\
+         2b3o3b3o2b$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+    ),
+    ("pentadecathlon", "x = 10, y = 3\n2bo4bo2b$2ob4ob2o$2bo4bo!"),
+    // Spaceships, all heading right or down and right.
+    ("glider", "x = 3, y = 3\nbo$2bo$3o!"),
+    ("lwss", "x = 5, y = 4\no2bo$4bo$o3bo$b4o!"),
+    ("mwss", "x = 6, y = 5\n2bo$o3bo$5bo$o4bo$b5o!"),
+    ("hwss", "x = 7, y = 5\n2b2o$o4bo$6bo$o5bo$b6o!"),
+    // Guns.
+    (
+        "gosper glider gun",
+        "x = 36, y = 9\n24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+         2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+    ),
+    // Methuselahs.
+    ("r-pentomino", "x = 3, y = 3\nb2o$2ob$bo!"),
+    ("acorn", "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!"),
+    ("diehard", "x = 8, y = 3\n6bob$2o6b$bo3b3o!"),
+];
+
+/// Names of the built in patterns.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PATTERNS.iter().map(|&(name, _)| name)
+}
+
+/// The built in pattern called `name`, ignoring case.
+pub fn get(name: &str) -> Option<Pattern> {
+    let (name, rle) = PATTERNS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name.trim()))?;
+    let mut pattern = Pattern::from_rle(rle).expect("built in patterns are valid RLE");
+    pattern.name = Some(name.to_string());
+    Some(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::library::*;
+    use crate::{Topology, Universe};
+
+    fn universe_with(name: &str, size: u32) -> Universe {
+        let mut universe = Universe::new();
+        universe.set_width(size);
+        universe.set_height(size);
+        universe.set_topology(Topology::DeadEdge);
+        universe.load(&get(name).unwrap()).unwrap();
+        universe
+    }
+
+    #[test]
+    fn every_pattern_parses() {
+        for name in names() {
+            let pattern = get(name).unwrap();
+            assert!(pattern.cells().count() > 0, "{} is empty", name);
+            assert_eq!(pattern.trimmed(), pattern, "{} has blank edges", name);
+        }
+        assert_eq!(get("Gosper Glider Gun").unwrap().width(), 36);
+        assert!(get("unicorn").is_none());
+    }
+
+    #[test]
+    fn patterns_behave() {
+        let period = |name: &str| {
+            let mut universe = universe_with(name, 24);
+            (0..32).for_each(|_| universe.tick());
+            universe.cycle_period()
+        };
+        assert_eq!(period("beehive"), Some(1.0));
+        assert_eq!(period("pulsar"), Some(3.0));
+        assert_eq!(period("pentadecathlon"), Some(15.0));
+
+        // A lightweight spaceship moves two cells every four generations.
+        let mut lwss = universe_with("lwss", 24);
+        let start = lwss.pattern();
+        (0..4).for_each(|_| lwss.tick());
+        assert_eq!(lwss.pattern(), start);
+        assert_eq!(
+            lwss.get_cells().iter().position(|&s| s == 1),
+            Some(10 * 24 + 11)
+        );
+
+        // The gun fires a glider every 30 generations.
+        let mut gun = universe_with("gosper glider gun", 48);
+        (0..60).for_each(|_| gun.tick());
+        assert_eq!(gun.population(), 36.0 + 2.0 * 5.0);
+
+        let mut diehard = universe_with("diehard", 48);
+        (0..130).for_each(|_| diehard.tick());
+        assert_eq!(diehard.population(), 0.0);
+    }
+}
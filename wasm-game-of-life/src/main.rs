@@ -0,0 +1,261 @@
+//! The Game of Life in a terminal.
+//!
+//! `life [PATTERN]` fills the terminal with random soup, or the RLE, Life
+//! 1.06 or plaintext pattern file given, and runs it. Build with
+//! `cargo run --features tui --bin life`.
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
+use std::error::Error;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use wasm_game_of_life::pattern::Pattern;
+use wasm_game_of_life::{Anchor, Universe};
+
+const HELP: &str = "space run/pause  n step  b back  +/- speed  r random  l load  \
+                    arrows move  enter toggle  u/U undo/redo  q quit";
+
+/// Share of cells alive in random soup.
+const DENSITY: f64 = 0.3;
+
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Lines under the board for the status and messages.
+const STATUS_LINES: u16 = 2;
+
+struct App {
+    universe: Universe,
+    /// Row and column of the cell being edited.
+    cursor: (u32, u32),
+    running: bool,
+    /// Time between generations while running.
+    delay: Duration,
+    /// Seed of the next random soup.
+    seed: u32,
+    /// The pattern file path being typed, after `l`.
+    prompt: Option<String>,
+    message: String,
+}
+
+impl App {
+    fn new(cols: u16, rows: u16) -> App {
+        let (width, height) = board_size(cols, rows);
+        App {
+            universe: Universe::random(width, height, DENSITY, 1),
+            cursor: (height / 2, width / 2),
+            running: true,
+            delay: Duration::from_millis(100),
+            seed: 2,
+            prompt: None,
+            message: String::new(),
+        }
+    }
+
+    fn load(&mut self, path: &str) {
+        let loaded = std::fs::read_to_string(path)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|raw| Pattern::parse(&raw))
+            .and_then(|pattern| self.universe.load(&pattern));
+        self.message = match loaded {
+            Ok(()) => format!("loaded {}", path),
+            Err(err) => format!("{}: {}", path, err),
+        };
+    }
+
+    fn tick(&mut self) {
+        self.universe.tick();
+        if let Some(report) = self.universe.cycle_report() {
+            self.message = report;
+        }
+    }
+
+    /// Fit the board to a resized terminal, keeping the middle.
+    fn fit(&mut self, cols: u16, rows: u16) {
+        let (width, height) = board_size(cols, rows);
+        self.universe.resize(width, height, Anchor::Center);
+        self.cursor = (self.cursor.0.min(height - 1), self.cursor.1.min(width - 1));
+    }
+
+    /// Move the cursor, wrapping around the board.
+    fn move_cursor(&mut self, rows: i64, cols: i64) {
+        let (height, width) = (self.universe.height() as i64, self.universe.width() as i64);
+        self.cursor = (
+            (self.cursor.0 as i64 + rows).rem_euclid(height) as u32,
+            (self.cursor.1 as i64 + cols).rem_euclid(width) as u32,
+        );
+    }
+
+    /// Handle a key press. Returns `false` to quit.
+    fn key(&mut self, key: KeyEvent) -> bool {
+        if let Some(path) = &mut self.prompt {
+            match key.code {
+                KeyCode::Char(c) => path.push(c),
+                KeyCode::Backspace => {
+                    path.pop();
+                }
+                KeyCode::Enter => {
+                    let path = self.prompt.take().unwrap();
+                    self.load(path.trim());
+                }
+                KeyCode::Esc => self.prompt = None,
+                _ => {}
+            }
+            return true;
+        }
+        self.message.clear();
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') => self.running = !self.running,
+            KeyCode::Char('n') | KeyCode::Char('.') => {
+                self.running = false;
+                self.tick();
+            }
+            KeyCode::Char('b') => {
+                self.running = false;
+                if !self.universe.step_back() {
+                    self.message = "no earlier generation kept".to_string();
+                }
+            }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.delay = (self.delay / 2).max(MIN_DELAY);
+            }
+            KeyCode::Char('-') => self.delay = (self.delay * 2).min(MAX_DELAY),
+            KeyCode::Char('r') => {
+                self.universe.randomize(DENSITY, self.seed);
+                self.message = format!("random soup, seed {}", self.seed);
+                self.seed = self.seed.wrapping_add(1);
+            }
+            KeyCode::Char('l') => {
+                self.running = false;
+                self.prompt = Some(String::new());
+            }
+            KeyCode::Up => self.move_cursor(-1, 0),
+            KeyCode::Down => self.move_cursor(1, 0),
+            KeyCode::Left => self.move_cursor(0, -1),
+            KeyCode::Right => self.move_cursor(0, 1),
+            KeyCode::Enter | KeyCode::Char('e') => {
+                self.universe
+                    .toggle_cell(self.cursor.0 as i32, self.cursor.1 as i32);
+            }
+            KeyCode::Char('u') => {
+                self.universe.undo();
+            }
+            KeyCode::Char('U') => {
+                self.universe.redo();
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Draw the board, two columns per cell, with the cursor cell in
+    /// reverse video and the status lines under it.
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        let glyph = |state| match state {
+            0 => "  ",
+            1 => "██",
+            _ => "▒▒",
+        };
+        let (width, height) = (self.universe.width(), self.universe.height());
+        for row in 0..height {
+            let line = |cols: std::ops::Range<u32>| {
+                cols.map(|col| glyph(self.universe.state(row, col)))
+                    .collect::<String>()
+            };
+            queue!(out, cursor::MoveTo(0, row as u16))?;
+            if row == self.cursor.0 {
+                let col = self.cursor.1;
+                queue!(
+                    out,
+                    Print(line(0..col)),
+                    SetAttribute(Attribute::Reverse),
+                    Print(line(col..col + 1)),
+                    SetAttribute(Attribute::Reset),
+                    Print(line(col + 1..width)),
+                )?;
+            } else {
+                queue!(out, Print(line(0..width)))?;
+            }
+        }
+
+        let status = format!(
+            "gen {}  pop {}  {}ms  {}  {}",
+            self.universe.generation(),
+            self.universe.population(),
+            self.delay.as_millis(),
+            if self.running { "running" } else { "paused" },
+            HELP,
+        );
+        let message = match &self.prompt {
+            Some(path) => format!("load pattern file: {}", path),
+            None => self.message.clone(),
+        };
+        // Lines wider than the board would wrap and scroll the screen.
+        let fit = |text: String| text.chars().take(2 * width as usize).collect::<String>();
+        queue!(
+            out,
+            cursor::MoveTo(0, height as u16),
+            Print(fit(status)),
+            terminal::Clear(ClearType::UntilNewLine),
+            cursor::MoveTo(0, height as u16 + 1),
+            Print(fit(message)),
+            terminal::Clear(ClearType::UntilNewLine),
+        )?;
+        out.flush()
+    }
+}
+
+/// Width and height of the board that fills a terminal.
+fn board_size(cols: u16, rows: u16) -> (u32, u32) {
+    let width = (cols / 2).max(1) as u32;
+    let height = rows.saturating_sub(STATUS_LINES).max(1) as u32;
+    (width, height)
+}
+
+fn run(app: &mut App, out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    let mut last_tick = Instant::now();
+    loop {
+        app.draw(out)?;
+        let wait = if app.running {
+            app.delay.saturating_sub(last_tick.elapsed())
+        } else {
+            MAX_DELAY
+        };
+        if event::poll(wait)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release && !app.key(key) => {
+                    return Ok(());
+                }
+                Event::Resize(cols, rows) => {
+                    app.fit(cols, rows);
+                    queue!(out, terminal::Clear(ClearType::All))?;
+                }
+                _ => {}
+            }
+        }
+        if app.running && last_tick.elapsed() >= app.delay {
+            app.tick();
+            last_tick = Instant::now();
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let (cols, rows) = terminal::size()?;
+    let mut app = App::new(cols, rows);
+    if let Some(path) = std::env::args().nth(1) {
+        app.load(&path);
+    }
+
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = run(&mut app, &mut out);
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
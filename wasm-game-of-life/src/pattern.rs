@@ -0,0 +1,612 @@
+use crate::rule::Rule;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::Write;
+
+/// Longest line written in RLE output, as recommended by the format.
+const RLE_LINE: usize = 70;
+
+/// Most cells a pattern read from a file may have, so a header can't ask
+/// for gigabytes.
+const MAX_CELLS: u32 = 1 << 26;
+
+/// A rectangular pattern of cell states, as read from or written to a
+/// pattern file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    pub name: Option<String>,
+    /// Rule from an RLE header, if the file named one.
+    pub rule: Option<Rule>,
+    width: u32,
+    height: u32,
+    /// Row-major cell states, see `Cell`.
+    cells: Vec<u8>,
+}
+
+impl Pattern {
+    /// Pattern from row-major cell states. Panics unless there are
+    /// `width * height` of them; sizes read from files go through `blank`.
+    pub fn new(width: u32, height: u32, cells: Vec<u8>) -> Pattern {
+        assert_eq!(cells.len(), (width * height) as usize);
+        Pattern {
+            name: None,
+            rule: None,
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// All dead pattern of the given size, or an error if it has more than
+    /// `MAX_CELLS` cells.
+    fn blank(width: u32, height: u32) -> Result<Pattern, Box<dyn Error>> {
+        match width.checked_mul(height) {
+            Some(size) if size <= MAX_CELLS => {
+                Ok(Pattern::new(width, height, vec![0; size as usize]))
+            }
+            _ => Err(format!("pattern {}x{} is too large", width, height).into()),
+        }
+    }
+
+    /// Pattern with the given live `(row, column)` cells, sized to fit them.
+    pub fn from_live_cells(live: &[(u32, u32)]) -> Result<Pattern, Box<dyn Error>> {
+        let extent = |side: u32| side.checked_add(1).ok_or("pattern is too large");
+        let (mut width, mut height) = (0, 0);
+        for &(row, col) in live {
+            width = width.max(extent(col)?);
+            height = height.max(extent(row)?);
+        }
+        let mut pattern = Pattern::blank(width, height)?;
+        for &(row, col) in live {
+            pattern.cells[(row * width + col) as usize] = 1;
+        }
+        Ok(pattern)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn state(&self, row: u32, column: u32) -> u8 {
+        self.cells[(row * self.width + column) as usize]
+    }
+
+    /// `(row, column, state)` of every cell that isn't dead.
+    pub fn cells(&self) -> impl Iterator<Item = (u32, u32, u8)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &s)| s != 0)
+            .map(move |(i, &s)| (i as u32 / self.width, i as u32 % self.width, s))
+    }
+
+    /// Mirrored left to right.
+    pub fn flipped(&self) -> Pattern {
+        let mut cells = self.cells.clone();
+        for row in cells.chunks_mut(self.width.max(1) as usize) {
+            row.reverse();
+        }
+        Pattern {
+            cells,
+            ..self.clone()
+        }
+    }
+
+    /// Turned clockwise by `quarter_turns` right angles.
+    pub fn rotated(&self, quarter_turns: u32) -> Pattern {
+        let mut pattern = self.clone();
+        for _ in 0..quarter_turns % 4 {
+            let (width, height) = (pattern.height, pattern.width);
+            let mut cells = vec![0; pattern.cells.len()];
+            for (row, col, state) in pattern.cells() {
+                // Row `row` becomes column `width - 1 - row`.
+                cells[(col * width + (width - 1 - row)) as usize] = state;
+            }
+            pattern = Pattern {
+                width,
+                height,
+                cells,
+                ..pattern
+            };
+        }
+        pattern
+    }
+
+    /// The smallest pattern holding every non-dead cell.
+    pub fn trimmed(&self) -> Pattern {
+        let (mut top, mut left, mut bottom, mut right) = (u32::MAX, u32::MAX, 0, 0);
+        for (row, col, _) in self.cells() {
+            top = top.min(row);
+            left = left.min(col);
+            bottom = bottom.max(row + 1);
+            right = right.max(col + 1);
+        }
+        if top == u32::MAX {
+            return Pattern {
+                width: 0,
+                height: 0,
+                cells: Vec::new(),
+                ..self.clone()
+            };
+        }
+        let (width, height) = (right - left, bottom - top);
+        let mut cells = Vec::with_capacity((width * height) as usize);
+        for row in top..bottom {
+            for col in left..right {
+                cells.push(self.state(row, col));
+            }
+        }
+        Pattern {
+            width,
+            height,
+            cells,
+            ..self.clone()
+        }
+    }
+
+    /// Read any of the supported formats, telling them apart by their
+    /// first line: `#Life 1.06`, an RLE `x = ...` header (possibly after
+    /// `#` comments) or otherwise plaintext.
+    pub fn parse(raw: &str) -> Result<Pattern, Box<dyn Error>> {
+        let first = raw.lines().map(str::trim).find(|l| !l.is_empty());
+        match first {
+            None => Err("pattern file is empty".into()),
+            Some(l) if l.starts_with("#Life 1.06") => Pattern::from_life106(raw),
+            Some(l) if l.starts_with('!') || !l.starts_with('#') && !l.starts_with('x') => {
+                Pattern::from_plaintext(raw)
+            }
+            Some(_) => Pattern::from_rle(raw),
+        }
+    }
+
+    /// Read run length encoded patterns, including multi-state Generations
+    /// patterns written with `.` and `A`-`X`.
+    pub fn from_rle(raw: &str) -> Result<Pattern, Box<dyn Error>> {
+        let mut name = None;
+        // Sized from the header as soon as it is read, so a huge header is
+        // refused before any run is expanded.
+        let mut pattern: Option<Pattern> = None;
+        let (mut row, mut col) = (0u32, 0u32);
+        let mut run = 0u32;
+        let mut done = false;
+
+        for (n, line) in raw.lines().enumerate() {
+            let line = line.trim();
+            let err =
+                |msg: String| -> Box<dyn Error> { format!("RLE line {}: {}", n + 1, msg).into() };
+            if done || line.is_empty() {
+                continue;
+            }
+            let pattern = match &mut pattern {
+                Some(pattern) => pattern,
+                None => {
+                    if let Some(comment) = line.strip_prefix('#') {
+                        if let Some(n) = comment.strip_prefix('N') {
+                            name = Some(n.trim().to_string());
+                        }
+                        continue;
+                    }
+                    let (width, height, rule) =
+                        parse_rle_header(line).map_err(|e| err(e.to_string()))?;
+                    let mut blank = Pattern::blank(width, height)?;
+                    blank.rule = rule;
+                    pattern = Some(blank);
+                    continue;
+                }
+            };
+
+            for c in line.chars() {
+                let count = run.max(1);
+                match c {
+                    '0'..='9' => {
+                        run = run
+                            .checked_mul(10)
+                            .and_then(|r| r.checked_add(c as u32 - '0' as u32))
+                            .ok_or_else(|| err(String::from("run count is too large")))?;
+                        continue;
+                    }
+                    'b' | '.' => col = col.saturating_add(count),
+                    'o' | 'A'..='X' => {
+                        let state = if c == 'o' { 1 } else { c as u8 - b'A' + 1 };
+                        let (width, height) = (pattern.width, pattern.height);
+                        for _ in 0..count {
+                            if row >= height || col >= width {
+                                return Err(format!(
+                                    "RLE cell at row {} column {} is outside the {}x{} header size",
+                                    row, col, width, height
+                                )
+                                .into());
+                            }
+                            pattern.cells[(row * width + col) as usize] = state;
+                            col += 1;
+                        }
+                    }
+                    '$' => {
+                        row = row.saturating_add(count);
+                        col = 0;
+                    }
+                    '!' => {
+                        done = true;
+                        break;
+                    }
+                    c if c.is_whitespace() => {}
+                    c => return Err(err(format!("unexpected '{}'", c))),
+                }
+                run = 0;
+            }
+        }
+
+        let mut pattern = pattern.ok_or("RLE file has no 'x = ..., y = ...' header")?;
+        pattern.name = name;
+        Ok(pattern)
+    }
+
+    /// Read Life 1.06: a `#Life 1.06` header then one `x y` pair per live
+    /// cell, where coordinates may be negative.
+    pub fn from_life106(raw: &str) -> Result<Pattern, Box<dyn Error>> {
+        let mut points = Vec::new();
+        for (n, line) in raw.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut coords = line.split_whitespace().map(str::parse::<i64>);
+            match (coords.next(), coords.next(), coords.next()) {
+                (Some(Ok(x)), Some(Ok(y)), None) => points.push((x, y)),
+                _ => {
+                    return Err(format!(
+                        "Life 1.06 line {}: expected 'x y' integers, got '{}'",
+                        n + 1,
+                        line
+                    )
+                    .into())
+                }
+            }
+        }
+        let left = points.iter().map(|p| p.0).min().unwrap_or(0);
+        let top = points.iter().map(|p| p.1).min().unwrap_or(0);
+        let mut live = Vec::with_capacity(points.len());
+        let offset = |n: i64, min: i64| n.checked_sub(min).and_then(|d| u32::try_from(d).ok());
+        for (x, y) in points {
+            match (offset(y, top), offset(x, left)) {
+                (Some(row), Some(col)) => live.push((row, col)),
+                _ => return Err("Life 1.06 pattern is too large".into()),
+            }
+        }
+        Pattern::from_live_cells(&live)
+    }
+
+    /// Read the `.cells` plaintext format: `!` comment lines, then rows of
+    /// `.` for dead and `O` for live cells.
+    pub fn from_plaintext(raw: &str) -> Result<Pattern, Box<dyn Error>> {
+        let mut name = None;
+        let mut live = Vec::new();
+        let mut rows = 0u32;
+        let mut width = 0u32;
+        for (n, line) in raw.lines().enumerate() {
+            let line = line.trim_end();
+            if let Some(comment) = line.strip_prefix('!') {
+                if let Some(n) = comment.strip_prefix("Name:") {
+                    name = Some(n.trim().to_string());
+                }
+                continue;
+            }
+            for (col, c) in line.chars().enumerate() {
+                match c {
+                    '.' => {}
+                    'O' | '*' => live.push((rows, col as u32)),
+                    c => {
+                        return Err(format!(
+                            "plaintext line {}: unexpected '{}', cells are '.' or 'O'",
+                            n + 1,
+                            c
+                        )
+                        .into())
+                    }
+                }
+            }
+            width = width.max(line.chars().count() as u32);
+            rows += 1;
+        }
+        // Keep trailing dead rows and columns the file spelled out.
+        let mut pattern = Pattern::blank(width, rows)?;
+        for (row, col) in live {
+            pattern.cells[(row * width + col) as usize] = 1;
+        }
+        pattern.name = name;
+        Ok(pattern)
+    }
+
+    /// Write RLE with an `x = ..., y = ..., rule = ...` header, using
+    /// `.`/`A`.. when there are Generations dying cells.
+    pub fn to_rle(&self) -> String {
+        let multi = self.cells.iter().any(|&s| s > 1);
+        let symbol = |state: u8| match (multi, state) {
+            (false, 0) => 'b',
+            (false, _) => 'o',
+            (true, 0) => '.',
+            (true, s) => (b'A' + s - 1) as char,
+        };
+
+        let mut out = String::new();
+        if let Some(name) = &self.name {
+            writeln!(out, "#N {}", name).unwrap();
+        }
+        write!(out, "x = {}, y = {}", self.width, self.height).unwrap();
+        if let Some(rule) = &self.rule {
+            write!(out, ", rule = {}", rule).unwrap();
+        }
+        out.push('\n');
+
+        // Runs of (count, symbol), dropping dead cells at row ends and
+        // merging blank rows into a single `n$`.
+        let mut runs: Vec<(u32, char)> = Vec::new();
+        let push = |runs: &mut Vec<(u32, char)>, count: u32, c: char| match runs.last_mut() {
+            Some((n, last)) if *last == c => *n += count,
+            _ => runs.push((count, c)),
+        };
+        for row in 0..self.height {
+            if row > 0 {
+                push(&mut runs, 1, '$');
+            }
+            let line = &self.cells[(row * self.width) as usize..((row + 1) * self.width) as usize];
+            let end = line.iter().rposition(|&s| s != 0).map_or(0, |i| i + 1);
+            for &state in &line[..end] {
+                push(&mut runs, 1, symbol(state));
+            }
+        }
+        while let Some((_, '$')) = runs.last() {
+            runs.pop();
+        }
+
+        let mut line = String::new();
+        for (count, c) in runs.into_iter().chain(std::iter::once((1, '!'))) {
+            let item = if count > 1 {
+                format!("{}{}", count, c)
+            } else {
+                c.to_string()
+            };
+            if line.len() + item.len() > RLE_LINE {
+                out.push_str(&line);
+                out.push('\n');
+                line.clear();
+            }
+            line.push_str(&item);
+        }
+        out.push_str(&line);
+        out.push('\n');
+        out
+    }
+
+    /// Write Life 1.06 with the top left of the pattern at `0 0`. Only live
+    /// cells are written, the format has no dying states.
+    pub fn to_life106(&self) -> String {
+        let mut out = String::from("#Life 1.06\n");
+        for (row, col, state) in self.cells() {
+            if state == 1 {
+                writeln!(out, "{} {}", col, row).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Write the `.cells` plaintext format. Dying cells are written dead.
+    pub fn to_plaintext(&self) -> String {
+        let mut out = String::new();
+        if let Some(name) = &self.name {
+            writeln!(out, "!Name: {}", name).unwrap();
+        }
+        for row in 0..self.height {
+            for col in 0..self.width {
+                out.push(if self.state(row, col) == 1 { 'O' } else { '.' });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Parse `x = 3, y = 3, rule = B3/S23` into the size and optional rule.
+fn parse_rle_header(line: &str) -> Result<(u32, u32, Option<Rule>), Box<dyn Error>> {
+    let (mut x, mut y, mut rule) = (None, None, None);
+    for field in line.split(',') {
+        let (key, value) = field
+            .split_once('=')
+            .ok_or_else(|| format!("expected 'key = value' in header, got '{}'", field.trim()))?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => {
+                x = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("bad width '{}'", value))?,
+                )
+            }
+            "y" => {
+                y = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("bad height '{}'", value))?,
+                )
+            }
+            "rule" => rule = Some(value.parse::<Rule>()?),
+            _ => {}
+        }
+    }
+    match (x, y) {
+        (Some(x), Some(y)) => Ok((x, y, rule)),
+        _ => Err("header needs both 'x = ' and 'y = '".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::*;
+
+    const GLIDER_RLE: &str = "#N Glider
+#C A comment
+x = 3, y = 3, rule = B3/S23
+bob$2bo$3o!
+";
+
+    fn glider() -> Pattern {
+        Pattern::from_live_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]).unwrap()
+    }
+
+    #[test]
+    fn rle_round_trip() {
+        let p = Pattern::parse(GLIDER_RLE).unwrap();
+        assert_eq!(p.name.as_deref(), Some("Glider"));
+        assert_eq!(p.rule, Some(Rule::conway()));
+        assert_eq!(
+            p.cells().collect::<Vec<_>>(),
+            glider().cells().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            p.to_rle(),
+            "#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n"
+        );
+        assert_eq!(Pattern::parse(&p.to_rle()).unwrap(), p);
+
+        // Blank rows collapse into a run of `$`, long runs wrap at 70.
+        let mut wide = Pattern::from_live_cells(&[(0, 0), (3, 0)]).unwrap();
+        assert_eq!(wide.to_rle(), "x = 1, y = 4\no3$o!\n");
+        wide = Pattern::from_live_cells(&(0..40).map(|i| (0, 2 * i)).collect::<Vec<_>>()).unwrap();
+        assert!(wide.to_rle().lines().all(|l| l.len() <= RLE_LINE));
+        assert_eq!(Pattern::from_rle(&wide.to_rle()).unwrap(), wide);
+    }
+
+    #[test]
+    fn rle_generations() {
+        let p = Pattern::parse("x = 3, y = 1, rule = B2/S345/C4\nA.C!").unwrap();
+        assert_eq!(p.rule.unwrap().states(), 4);
+        assert_eq!(p.cells().collect::<Vec<_>>(), vec![(0, 0, 1), (0, 2, 3)]);
+        assert_eq!(p.to_rle(), "x = 3, y = 1, rule = B2/S345/C4\nA.C!\n");
+    }
+
+    #[test]
+    fn rle_errors() {
+        let err = |s: &str| Pattern::from_rle(s).unwrap_err().to_string();
+        assert_eq!(err("x = 3, y = 3\nbo$2bq!"), "RLE line 2: unexpected 'q'");
+        assert_eq!(
+            err("x = 3\nbo!"),
+            "RLE line 1: header needs both 'x = ' and 'y = '"
+        );
+        assert_eq!(
+            err("x = 2, y = 1\n3o!"),
+            "RLE cell at row 0 column 2 is outside the 2x1 header size"
+        );
+        assert!(err("x = 2, y = 1, rule = B9/S\no!").starts_with("RLE line 1: invalid rule"));
+        assert_eq!(
+            err("#C nothing\n"),
+            "RLE file has no 'x = ..., y = ...' header"
+        );
+        // Sizes that overflow or would take gigabytes are refused.
+        assert_eq!(
+            err("x = 100000, y = 100000\no!"),
+            "pattern 100000x100000 is too large"
+        );
+        assert_eq!(
+            err("x = 60000, y = 60000\no!"),
+            "pattern 60000x60000 is too large"
+        );
+        assert_eq!(
+            err("x = 2, y = 1\n4294967295o!"),
+            "RLE cell at row 0 column 2 is outside the 2x1 header size"
+        );
+        assert_eq!(
+            err("x = 4294967295, y = 1\n4294967295o!"),
+            "pattern 4294967295x1 is too large"
+        );
+        assert!(err("x = 2, y = 1\n4294967295b4294967295bo!").contains("outside"));
+        assert!(err("x = 2, y = 1\n4294967295$4294967295$o!").contains("outside"));
+    }
+
+    #[test]
+    fn life106() {
+        let p = Pattern::parse("#Life 1.06\n0 -1\n1 0\n-1 1\n0 1\n1 1\n").unwrap();
+        assert_eq!(
+            p.cells().collect::<Vec<_>>(),
+            glider().cells().collect::<Vec<_>>()
+        );
+        assert_eq!(Pattern::parse(&p.to_life106()).unwrap(), p);
+        assert_eq!(
+            Pattern::parse("#Life 1.06\n0 1\n2\n")
+                .unwrap_err()
+                .to_string(),
+            "Life 1.06 line 3: expected 'x y' integers, got '2'"
+        );
+        for far in &[
+            "#Life 1.06\n9223372036854775807 0\n-9223372036854775808 0\n",
+            "#Life 1.06\n2147483647 0\n-2147483648 0\n",
+            "#Life 1.06\n0 2147483648\n0 -2147483648\n",
+        ] {
+            assert!(Pattern::parse(far).is_err(), "{}", far);
+        }
+        assert!(Pattern::from_live_cells(&[(0, u32::MAX)]).is_err());
+    }
+
+    #[test]
+    fn plaintext() {
+        let raw = "!Name: Glider\n!\n.O.\n..O\nOOO\n...\n";
+        let p = Pattern::parse(raw).unwrap();
+        assert_eq!(p.name.as_deref(), Some("Glider"));
+        assert_eq!((p.width(), p.height()), (3, 4));
+        assert_eq!(p.to_plaintext(), raw.replace("!\n", ""));
+        assert_eq!(p.trimmed().cells, glider().cells);
+        assert_eq!(
+            Pattern::parse(".O.\n.x.\n").unwrap_err().to_string(),
+            "plaintext line 2: unexpected 'x', cells are '.' or 'O'"
+        );
+    }
+
+    #[test]
+    fn transforms() {
+        let l = Pattern::parse("x = 2, y = 3\no$o$2o!").unwrap();
+        assert_eq!(l.flipped().to_plaintext(), ".O\n.O\nOO\n");
+        assert_eq!(l.rotated(1).to_plaintext(), "OOO\nO..\n");
+        assert_eq!(l.rotated(2).to_plaintext(), "OO\n.O\n.O\n");
+        assert_eq!(l.rotated(3).to_plaintext(), "..O\nOOO\n");
+        assert_eq!(l.rotated(4), l);
+    }
+
+    #[test]
+    fn load_into_universe() {
+        let mut universe = crate::Universe::new();
+        universe.set_width(8);
+        universe.set_height(6);
+        universe
+            .load(&Pattern::parse("x = 3, y = 1, rule = B36/S23\n3o!").unwrap())
+            .unwrap();
+        assert_eq!(universe.rule(), "B36/S23");
+        assert_eq!(universe.to_string().lines().nth(2), Some("◻◻◼◼◼◻◻◻"));
+        assert_eq!(universe.to_rle(), "x = 3, y = 1, rule = B36/S23\n3o!\n");
+        assert_eq!(universe.to_life106(), "#Life 1.06\n0 0\n1 0\n2 0\n");
+
+        let wide = Pattern::from_live_cells(&[(0, 0), (0, 8)]).unwrap();
+        assert_eq!(
+            universe.load(&wide).unwrap_err().to_string(),
+            "pattern 9x1 doesn't fit a 8x6 universe"
+        );
+        assert_eq!(
+            universe.paste(&glider(), 4, 0).unwrap_err().to_string(),
+            "pattern 3x3 at row 4 column 0 doesn't fit a 8x6 universe"
+        );
+        assert_eq!(
+            universe
+                .paste(&glider(), u32::MAX, u32::MAX)
+                .unwrap_err()
+                .to_string(),
+            "pattern 3x3 at row 4294967295 column 4294967295 doesn't fit a 8x6 universe"
+        );
+        let dying = Pattern::parse("x = 1, y = 1\nB!").unwrap();
+        assert_eq!(
+            universe.load(&dying).unwrap_err().to_string(),
+            "pattern cell state 2 isn't valid under B36/S23"
+        );
+    }
+}
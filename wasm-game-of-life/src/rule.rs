@@ -0,0 +1,206 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An outer totalistic Life-like rule, optionally with Generations decay.
+///
+/// Cell states are `0` dead, `1` alive and, for Generations rules with
+/// `states > 2`, `2..states` dying. A dying cell ignores its neighbours,
+/// ages by one state each tick and is dead after the last one. Only alive
+/// cells count as neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rule {
+    /// Bit `n` is set when a dead cell with `n` live neighbours is born.
+    birth: u16,
+    /// Bit `n` is set when a live cell with `n` live neighbours survives.
+    survival: u16,
+    states: u8,
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::conway()
+    }
+}
+
+fn digits(s: &str, rule: &str) -> Result<u16, Box<dyn Error>> {
+    let mut mask = 0u16;
+    for c in s.chars() {
+        match c.to_digit(10) {
+            Some(n) if n <= 8 => mask |= 1 << n,
+            _ => {
+                return Err(format!(
+                    "invalid rule '{}': '{}' is not a neighbour count 0-8",
+                    rule, c
+                )
+                .into())
+            }
+        }
+    }
+    Ok(mask)
+}
+
+fn state_count(s: &str, rule: &str) -> Result<u8, Box<dyn Error>> {
+    match s.parse::<u8>() {
+        Ok(n) if n >= 2 => Ok(n),
+        _ => Err(format!(
+            "invalid rule '{}': state count '{}' must be a number from 2 to 255",
+            rule, s
+        )
+        .into()),
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Box<dyn Error>;
+
+    /// Parse `B3/S23` notation, with an optional Generations state count as
+    /// in `B2/S345/C4`, or the older survival-first `23/3` and `345/2/4`.
+    fn from_str(s: &str) -> Result<Rule, Box<dyn Error>> {
+        let rule = s.trim();
+        let parts: Vec<&str> = rule.split('/').map(str::trim).collect();
+        if !(2..=3).contains(&parts.len()) {
+            return Err(format!("invalid rule '{}': expected B.../S... or S/B", rule).into());
+        }
+
+        let tagged = |tag: char| {
+            parts
+                .iter()
+                .find(|p| p.starts_with(tag) || p.starts_with(tag.to_ascii_lowercase()))
+                .map(|p| &p[1..])
+        };
+        let (birth, survival, states) = match (tagged('B'), tagged('S')) {
+            (Some(b), Some(s)) => {
+                let states = match parts.get(2) {
+                    Some(c) => state_count(c.trim_start_matches(['C', 'c', 'G', 'g']), rule)?,
+                    None => 2,
+                };
+                (b, s, states)
+            }
+            (None, None) => {
+                let states = match parts.get(2) {
+                    Some(c) => state_count(c, rule)?,
+                    None => 2,
+                };
+                (parts[1], parts[0], states)
+            }
+            _ => {
+                return Err(format!("invalid rule '{}': needs both a B and an S part", rule).into())
+            }
+        };
+
+        Ok(Rule {
+            birth: digits(birth, rule)?,
+            survival: digits(survival, rule)?,
+            states,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |mask: u16| -> String {
+            (0..=8)
+                .filter(|n| mask & (1 << n) != 0)
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+impl Rule {
+    /// Conway's Game of Life, B3/S23.
+    pub fn conway() -> Rule {
+        Rule {
+            birth: 1 << 3,
+            survival: 1 << 2 | 1 << 3,
+            states: 2,
+        }
+    }
+
+    /// Number of cell states, 2 for plain Life-like rules.
+    pub fn states(&self) -> u8 {
+        self.states
+    }
+
+    pub fn is_birth(&self, live_neighbors: u8) -> bool {
+        self.birth & (1 << live_neighbors) != 0
+    }
+
+    pub fn is_survival(&self, live_neighbors: u8) -> bool {
+        self.survival & (1 << live_neighbors) != 0
+    }
+
+    /// State of a cell in `state` with `live_neighbors` next generation.
+    pub fn next(&self, state: u8, live_neighbors: u8) -> u8 {
+        match state {
+            0 if self.is_birth(live_neighbors) => 1,
+            0 => 0,
+            1 if self.is_survival(live_neighbors) => 1,
+            // Dying cells age until they run out of states.
+            _ if state + 1 < self.states => state + 1,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rule::*;
+
+    #[test]
+    fn parse_and_display() {
+        let rule = |s: &str| s.parse::<Rule>().unwrap().to_string();
+        assert_eq!(rule("B3/S23"), "B3/S23");
+        assert_eq!(rule("b36/s23"), "B36/S23");
+        assert_eq!(rule("S23/B3"), "B3/S23");
+        assert_eq!(rule("23/3"), "B3/S23");
+        assert_eq!(rule("B2/S"), "B2/S");
+        assert_eq!(rule("B2/S345/C4"), "B2/S345/C4");
+        assert_eq!(rule("345/2/4"), "B2/S345/C4");
+        assert_eq!(rule("B3/S23/C2"), "B3/S23");
+        assert_eq!("B3/S23".parse::<Rule>().unwrap(), Rule::conway());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |s: &str| s.parse::<Rule>().unwrap_err().to_string();
+        assert_eq!(
+            err("B39/S23"),
+            "invalid rule 'B39/S23': '9' is not a neighbour count 0-8"
+        );
+        assert_eq!(err("B3"), "invalid rule 'B3': expected B.../S... or S/B");
+        assert_eq!(
+            err("B3/23"),
+            "invalid rule 'B3/23': needs both a B and an S part"
+        );
+        assert!(err("B2/S345/C1").contains("state count"));
+        assert!(err("B2/S345/Cx").contains("state count"));
+    }
+
+    #[test]
+    fn transitions() {
+        let life = Rule::conway();
+        assert_eq!(life.next(0, 3), 1);
+        assert_eq!(life.next(0, 2), 0);
+        assert_eq!(life.next(1, 2), 1);
+        assert_eq!(life.next(1, 4), 0);
+
+        let brians_brain: Rule = "B2/S/C3".parse().unwrap();
+        assert_eq!(brians_brain.next(0, 2), 1);
+        assert_eq!(brians_brain.next(1, 2), 2);
+        // Dying cells ignore their neighbours.
+        assert_eq!(brians_brain.next(2, 2), 0);
+        assert_eq!(brians_brain.next(2, 3), 0);
+
+        let star_wars: Rule = "B2/S345/C4".parse().unwrap();
+        assert_eq!(star_wars.next(1, 0), 2);
+        assert_eq!(star_wars.next(2, 3), 3);
+        assert_eq!(star_wars.next(3, 3), 0);
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Generations of population and state hashes kept.
+pub const WINDOW: usize = 10_000;
+
+/// A repeating sequence of generations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    /// First generation of the cycle.
+    pub start: u64,
+    /// Generations before it repeats, 1 for a still life.
+    pub period: u64,
+}
+
+/// Population of consecutive generations, with cycle detection by hashing
+/// each generation's cells.
+#[derive(Default)]
+pub struct Stats {
+    /// Generation of the first entry in `generations`.
+    first: u64,
+    /// Population and state hash of each generation from `first` on. The
+    /// hash is `None` when the state can't be hashed.
+    generations: VecDeque<(u64, Option<u64>)>,
+    /// The first generation in the window with each hash.
+    seen: HashMap<u64, u64>,
+    cycle: Option<Cycle>,
+}
+
+impl Stats {
+    /// Forget everything, for when cells change other than by ticking.
+    pub fn reset(&mut self) {
+        self.generations.clear();
+        self.seen.clear();
+        self.cycle = None;
+    }
+
+    /// Record `generation`. Anything other than the generation after the
+    /// last one recorded starts afresh.
+    pub fn record(&mut self, generation: u64, population: u64, hash: Option<u64>) {
+        if self.generations.is_empty() || generation != self.first + self.generations.len() as u64 {
+            self.reset();
+            self.first = generation;
+        }
+        if self.generations.len() == WINDOW {
+            if let Some((_, Some(oldest))) = self.generations.pop_front() {
+                if self.seen.get(&oldest) == Some(&self.first) {
+                    self.seen.remove(&oldest);
+                }
+            }
+            self.first += 1;
+        }
+        if let Some(hash) = hash {
+            let start = *self.seen.entry(hash).or_insert(generation);
+            if self.cycle.is_none() && start != generation {
+                self.cycle = Some(Cycle {
+                    start,
+                    period: generation - start,
+                });
+            }
+        }
+        self.generations.push_back((population, hash));
+    }
+
+    /// Population of the latest generation.
+    pub fn population(&self) -> u64 {
+        self.generations.back().map_or(0, |g| g.0)
+    }
+
+    /// Generation of the first population in `populations`.
+    pub fn first_generation(&self) -> u64 {
+        self.first
+    }
+
+    pub fn populations(&self) -> impl Iterator<Item = u64> + '_ {
+        self.generations.iter().map(|g| g.0)
+    }
+
+    /// The first cycle found since the last reset.
+    pub fn cycle(&self) -> Option<Cycle> {
+        self.cycle
+    }
+
+    /// What the cycle is, e.g. `period-2 oscillator reached at gen 412`.
+    pub fn report(&self) -> Option<String> {
+        let Cycle { start, period } = self.cycle?;
+        let died = self
+            .generations
+            .get(start.saturating_sub(self.first) as usize)
+            .is_some_and(|g| g.0 == 0);
+        Some(match period {
+            1 if died => format!("died out at gen {}", start),
+            1 => format!("still life reached at gen {}", start),
+            p => format!("period-{} oscillator reached at gen {}", p, start),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::*;
+
+    #[test]
+    fn finds_the_first_repeat() {
+        let mut stats = Stats::default();
+        for (generation, hash) in [10, 11, 12, 13, 12, 13].iter().enumerate() {
+            stats.record(generation as u64, *hash, Some(*hash));
+        }
+        assert_eq!(
+            stats.cycle(),
+            Some(Cycle {
+                start: 2,
+                period: 2
+            })
+        );
+        assert_eq!(
+            stats.report().unwrap(),
+            "period-2 oscillator reached at gen 2"
+        );
+        assert_eq!(
+            stats.populations().collect::<Vec<_>>(),
+            [10, 11, 12, 13, 12, 13]
+        );
+
+        // Out of order generations start over.
+        stats.record(3, 7, Some(7));
+        assert_eq!(stats.cycle(), None);
+        assert_eq!(stats.first_generation(), 3);
+        stats.record(4, 0, Some(0));
+        stats.record(5, 0, Some(0));
+        assert_eq!(stats.report().unwrap(), "died out at gen 4");
+
+        // Without hashes only populations are kept.
+        stats.reset();
+        stats.record(0, 5, None);
+        stats.record(1, 5, None);
+        assert_eq!((stats.population(), stats.cycle()), (5, None));
+    }
+
+    #[test]
+    fn window_forgets_old_generations() {
+        let mut stats = Stats::default();
+        stats.record(0, 1, Some(u64::MAX));
+        for generation in 1..=WINDOW as u64 {
+            stats.record(generation, 1, Some(generation));
+        }
+        assert_eq!(stats.first_generation(), 1);
+        assert_eq!(stats.populations().count(), WINDOW);
+        // Generation 0 left the window, so its state coming back isn't a
+        // cycle any more.
+        stats.record(WINDOW as u64 + 1, 1, Some(u64::MAX));
+        assert_eq!(stats.cycle(), None);
+    }
+}
@@ -0,0 +1,129 @@
+//! Property tests of `Universe` over random boards and rules.
+
+use proptest::prelude::*;
+use wasm_game_of_life::pattern::Pattern;
+use wasm_game_of_life::{library, Anchor, Kernel, Topology, Universe};
+
+fn topology() -> impl Strategy<Value = Topology> {
+    prop_oneof![
+        Just(Topology::Torus),
+        Just(Topology::DeadEdge),
+        Just(Topology::KleinBottle),
+        Just(Topology::Growing),
+    ]
+}
+
+fn anchor() -> impl Strategy<Value = Anchor> {
+    prop_oneof![
+        Just(Anchor::TopLeft),
+        Just(Anchor::TopRight),
+        Just(Anchor::Center),
+        Just(Anchor::BottomLeft),
+        Just(Anchor::BottomRight),
+    ]
+}
+
+/// A two state rule in B/S notation.
+fn rule() -> impl Strategy<Value = String> {
+    let counts = || proptest::collection::btree_set(0..=8u8, 0..=9);
+    (counts(), counts()).prop_map(|(birth, survival)| {
+        let digits = |counts: std::collections::BTreeSet<u8>| {
+            counts.iter().map(|n| n.to_string()).collect::<String>()
+        };
+        format!("B{}/S{}", digits(birth), digits(survival))
+    })
+}
+
+/// Width, height, density and seed of random soup up to 40x40.
+fn soup() -> impl Strategy<Value = (u32, u32, f64, u32)> {
+    (1..=40u32, 1..=40u32, 0.0..=1.0f64, any::<u32>())
+}
+
+proptest! {
+    #[test]
+    fn still_lifes_stay_still(
+        name in prop_oneof![Just("block"), Just("beehive"), Just("loaf"), Just("boat")],
+        width in 6..=30u32,
+        height in 6..=30u32,
+        row in -10..40i64,
+        column in -10..40i64,
+    ) {
+        let mut universe = Universe::with_size(width, height);
+        universe.stamp(&library::get(name).unwrap(), row, column);
+        let start = universe.get_cells();
+        for _ in 0..8 {
+            universe.tick();
+            prop_assert_eq!(&universe.get_cells(), &start);
+        }
+        prop_assert_eq!(universe.cycle_period(), Some(1.0));
+    }
+
+    #[test]
+    fn kernels_agree((width, height, density, seed) in soup(), rule in rule(), topology in topology()) {
+        let mut universes: Vec<_> = [Kernel::CellByCell, Kernel::Bitwise, Kernel::Parallel]
+            .iter()
+            .map(|&kernel| {
+                let mut universe = Universe::random(width, height, density, seed);
+                universe.set_rule(&rule).unwrap();
+                universe.set_topology(topology);
+                universe.set_kernel(kernel);
+                universe
+            })
+            .collect();
+        for generation in 1..=6 {
+            universes.iter_mut().for_each(|u| u.tick());
+            for universe in &universes[1..] {
+                prop_assert_eq!(
+                    universe.get_cells(),
+                    universes[0].get_cells(),
+                    "{:?} running {} on {:?} at gen {}", universe.kernel(), rule, topology, generation
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn toggling_twice_changes_nothing(
+        (width, height, density, seed) in soup(),
+        row in -100..100i32,
+        column in -100..100i32,
+    ) {
+        // Anywhere on a torus is on the board.
+        let mut universe = Universe::random(width, height, density, seed);
+        let start = universe.get_cells();
+        prop_assert!(universe.toggle_cell(row, column));
+        prop_assert_ne!(&universe.get_cells(), &start);
+        prop_assert!(universe.toggle_cell(row, column));
+        prop_assert_eq!(&universe.get_cells(), &start);
+        prop_assert!(universe.undo() && universe.undo());
+        prop_assert_eq!(&universe.get_cells(), &start);
+    }
+
+    #[test]
+    fn growing_and_shrinking_back_keeps_every_cell(
+        (width, height, density, seed) in soup(),
+        wider in 0..20u32,
+        taller in 0..20u32,
+        anchor in anchor(),
+    ) {
+        let mut universe = Universe::random(width, height, density, seed);
+        let start = universe.get_cells();
+        universe.resize(width + wider, height + taller, anchor);
+        prop_assert_eq!(universe.population(), start.iter().filter(|&&s| s == 1).count() as f64);
+        universe.resize(width, height, anchor);
+        prop_assert_eq!(universe.get_cells(), start);
+    }
+
+    #[test]
+    fn rle_round_trips((width, height, density, seed) in soup()) {
+        let universe = Universe::random(width, height, density, seed);
+        let pattern = Pattern::parse(&universe.to_rle()).unwrap();
+        prop_assert_eq!(pattern, universe.pattern());
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_soup((width, height, density, seed) in soup()) {
+        let soup = Universe::random(width, height, density, seed);
+        prop_assert_eq!(soup.get_cells(), Universe::random(width, height, density, seed).get_cells());
+    }
+}
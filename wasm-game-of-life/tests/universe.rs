@@ -0,0 +1,297 @@
+//! Native tests of the simulation core through `Universe`'s public API.
+
+use wasm_game_of_life::pattern::Pattern;
+use wasm_game_of_life::{library, Anchor, Kernel, Topology, Universe};
+
+const KERNELS: [Kernel; 3] = [Kernel::CellByCell, Kernel::Bitwise, Kernel::Parallel];
+
+/// A `width` by `height` board with the library pattern `name` stamped
+/// with its top left at `row`, `column`.
+fn board(
+    name: &str,
+    width: u32,
+    height: u32,
+    topology: Topology,
+    row: i64,
+    column: i64,
+) -> Universe {
+    let mut universe = Universe::with_size(width, height);
+    universe.set_topology(topology);
+    universe.stamp(&library::get(name).unwrap(), row, column);
+    universe
+}
+
+/// Live cells as `(row, column)`, in row order.
+fn live(universe: &Universe) -> Vec<(u32, u32)> {
+    let width = universe.width();
+    let cells = universe.get_cells();
+    (0..cells.len() as u32)
+        .filter(|&i| cells[i as usize] == 1)
+        .map(|i| (i / width, i % width))
+        .collect()
+}
+
+/// `cells` moved down `rows` and right `columns` on a torus.
+fn shifted(universe: &Universe, cells: &[(u32, u32)], rows: u32, columns: u32) -> Vec<(u32, u32)> {
+    let (width, height) = (universe.width(), universe.height());
+    let mut cells: Vec<_> = cells
+        .iter()
+        .map(|&(r, c)| ((r + rows) % height, (c + columns) % width))
+        .collect();
+    cells.sort_unstable();
+    cells
+}
+
+#[test]
+fn oscillators_repeat_with_their_period() {
+    let oscillators = [
+        ("blinker", 2),
+        ("toad", 2),
+        ("beacon", 2),
+        ("pulsar", 3),
+        ("pentadecathlon", 15),
+    ];
+    for &kernel in &KERNELS {
+        for &(name, period) in &oscillators {
+            let mut universe = board(name, 24, 24, Topology::DeadEdge, 6, 6);
+            universe.set_kernel(kernel);
+            let start = live(&universe);
+            universe.tick();
+            assert_ne!(live(&universe), start, "{} didn't change", name);
+            for generation in 2..=10 * period {
+                universe.tick();
+                if generation % period == 0 {
+                    assert_eq!(live(&universe), start, "{} at gen {}", name, generation);
+                }
+            }
+            assert_eq!(universe.cycle_period(), Some(period as f64), "{}", name);
+        }
+    }
+}
+
+#[test]
+fn spaceships_travel_around_the_torus() {
+    // Each moves `down` rows and `right` columns every four generations.
+    let spaceships = [
+        ("glider", 1, 1),
+        ("lwss", 0, 2),
+        ("mwss", 0, 2),
+        ("hwss", 0, 2),
+    ];
+    for &kernel in &KERNELS {
+        for &(name, down, right) in &spaceships {
+            let mut universe = board(name, 40, 20, Topology::Torus, 5, 5);
+            universe.set_kernel(kernel);
+            let start = live(&universe);
+            for lap in 1..=40 {
+                (0..4).for_each(|_| universe.tick());
+                let expected = shifted(&universe, &start, lap * down, lap * right);
+                assert_eq!(live(&universe), expected, "{} after {} laps", name, lap);
+            }
+            // Forty laps takes every ship right round the board.
+            assert_eq!(live(&universe), start, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn edges_follow_the_topology() {
+    // A blinker standing on the left edge keeps going on a torus, where its
+    // other half is on the right edge, but dies against a dead edge.
+    let mut torus = Universe::with_size(8, 8);
+    torus.set_cells(&[(3, 0), (4, 0), (5, 0)]);
+    torus.tick();
+    assert_eq!(live(&torus), [(4, 0), (4, 1), (4, 7)]);
+    torus.tick();
+    assert_eq!(live(&torus), [(3, 0), (4, 0), (5, 0)]);
+
+    let mut dead = Universe::with_size(8, 8);
+    dead.set_topology(Topology::DeadEdge);
+    dead.set_cells(&[(3, 0), (4, 0), (5, 0)]);
+    dead.tick();
+    assert_eq!(live(&dead), [(4, 0), (4, 1)]);
+    dead.tick();
+    assert_eq!(dead.population(), 0.0);
+
+    // Across the top and bottom of a Klein bottle the glider comes back
+    // mirrored, heading down and left instead of down and right.
+    let glider = library::get("glider").unwrap();
+    let cells = |pattern: &Pattern| pattern.cells().map(|(r, c, _)| (r, c)).collect::<Vec<_>>();
+    for &kernel in &KERNELS {
+        let mut klein = board("glider", 12, 12, Topology::KleinBottle, 6, 4);
+        klein.set_kernel(kernel);
+        (0..36).for_each(|_| klein.tick());
+        assert_eq!(cells(&klein.pattern()), cells(&glider.flipped()));
+    }
+
+    // A growing board makes room, so the glider never wraps.
+    let mut growing = board("glider", 8, 8, Topology::Growing, 4, 4);
+    (0..40).for_each(|_| growing.tick());
+    assert!(growing.width() > 8 && growing.height() > 8);
+    assert_eq!(cells(&growing.pattern()), cells(&glider));
+}
+
+#[test]
+fn toggle_cell_flips_and_undoes() {
+    let mut universe = Universe::with_size(5, 5);
+    universe.toggle_cell(2, 3);
+    assert_eq!(universe.state(2, 3), 1);
+    assert_eq!(live(&universe), [(2, 3)]);
+    universe.toggle_cell(2, 3);
+    assert_eq!(universe.population(), 0.0);
+
+    universe.toggle_cell(0, 0);
+    universe.toggle_cell(4, 4);
+    assert!(universe.undo());
+    assert_eq!(live(&universe), [(0, 0)]);
+    assert!(universe.redo());
+    assert_eq!(live(&universe), [(0, 0), (4, 4)]);
+
+    // A dying cell under a Generations rule toggles to dead.
+    universe.set_rule("B2/S/C3").unwrap();
+    universe.tick();
+    assert_eq!(universe.state(0, 0), 2);
+    universe.toggle_cell(0, 0);
+    assert_eq!(universe.state(0, 0), 0);
+}
+
+#[test]
+fn resize_keeps_cells_by_the_anchor() {
+    let corners = [
+        (Anchor::TopLeft, (0, 0)),
+        (Anchor::TopRight, (0, 3)),
+        (Anchor::Center, (1, 1)),
+        (Anchor::BottomLeft, (2, 0)),
+        (Anchor::BottomRight, (2, 3)),
+    ];
+    for &(anchor, (top, left)) in &corners {
+        let mut universe = board("glider", 6, 5, Topology::Torus, 1, 1);
+        let start = live(&universe);
+        universe.resize(9, 7, anchor);
+        assert_eq!((universe.width(), universe.height()), (9, 7));
+        let moved: Vec<_> = start.iter().map(|&(r, c)| (r + top, c + left)).collect();
+        assert_eq!(live(&universe), moved, "{:?}", anchor);
+
+        // Shrinking back the same way puts everything back.
+        universe.resize(6, 5, anchor);
+        assert_eq!(live(&universe), start, "{:?}", anchor);
+    }
+
+    // Cells that end up off the board are gone for good.
+    let mut universe = board("block", 4, 4, Topology::Torus, 2, 2);
+    universe.resize(3, 3, Anchor::TopLeft);
+    assert_eq!(live(&universe), [(2, 2)]);
+    universe.resize(4, 4, Anchor::TopLeft);
+    assert_eq!(live(&universe), [(2, 2)]);
+
+    // Sides never drop below one cell.
+    universe.resize(0, 0, Anchor::Center);
+    assert_eq!((universe.width(), universe.height()), (1, 1));
+    let mut growing = Universe::new();
+    growing.set_topology(Topology::Growing);
+    growing.set_width(0);
+    growing.set_height(0);
+    assert_eq!((growing.width(), growing.height()), (1, 1));
+    growing.tick();
+
+    // Nor grow past 16384, where width * height would overflow a u32.
+    let mut wide = Universe::with_size(u32::MAX, 0);
+    assert_eq!((wide.width(), wide.height()), (16384, 1));
+    wide.set_cells(&[(0, 0), (0, 16383)]);
+    assert_eq!(live(&wide), [(0, 0), (0, 16383)]);
+    wide.tick();
+    wide.resize(3, u32::MAX, Anchor::TopLeft);
+    assert_eq!((wide.width(), wide.height()), (3, 16384));
+    wide.set_height(u32::MAX);
+    wide.set_width(u32::MAX);
+    assert_eq!((wide.width(), wide.height()), (16384, 16384));
+    let soup = Universe::random(1, u32::MAX, 0.5, 7);
+    assert_eq!((soup.width(), soup.height()), (1, 16384));
+}
+
+#[test]
+fn edits_wrap_or_refuse_past_the_edges() {
+    let mut torus = Universe::with_size(6, 6);
+    assert!(torus.toggle_cell(-1, 7));
+    assert_eq!(live(&torus), [(5, 1)]);
+    // Gliders launched near the edges wrap instead of panicking.
+    torus.launch_glider(5, 5);
+    torus.launch_glider(i32::MAX, i32::MIN);
+    assert!(torus.population() > 5.0);
+
+    let mut dead = Universe::with_size(6, 6);
+    dead.set_topology(Topology::DeadEdge);
+    assert!(!dead.toggle_cell(6, 0));
+    assert!(!dead.toggle_cell(-1, 0));
+    assert!(!dead.set_cell(0, 6, 1));
+    assert!(!dead.undo());
+    dead.launch_glider(5, 5);
+    assert_eq!(live(&dead), [(5, 5)]);
+
+    // Only states of the rule can be set.
+    assert!(!dead.set_cell(0, 0, 2));
+    dead.set_rule("B2/S/C3").unwrap();
+    assert!(dead.set_cell(0, 0, 2));
+    assert_eq!(dead.state(0, 0), 2);
+}
+
+#[test]
+fn rectangles_and_lines() {
+    let mut torus = Universe::with_size(6, 6);
+    torus.fill_rect(4, 4, 3, 3);
+    let corners = [0, 4, 5];
+    let expected: Vec<_> = corners
+        .iter()
+        .flat_map(|&r| corners.iter().map(move |&c| (r, c)))
+        .collect();
+    assert_eq!(live(&torus), expected);
+    torus.clear_rect(5, 5, 1, 1);
+    assert_eq!(torus.population(), 8.0);
+    // A rectangle bigger than a wrapping board covers it once.
+    torus.fill_rect(i32::MIN, -3, u32::MAX, u32::MAX);
+    assert_eq!(torus.population(), 36.0);
+    assert!(torus.undo());
+    assert_eq!(torus.population(), 8.0);
+
+    let mut dead = Universe::with_size(8, 8);
+    dead.set_topology(Topology::DeadEdge);
+    dead.fill_rect(-2, -2, 4, 4);
+    assert_eq!(live(&dead), [(0, 0), (0, 1), (1, 0), (1, 1)]);
+    // Painting nothing, or nothing new, isn't an edit.
+    dead.fill_rect(100, 100, 5, 5);
+    dead.fill_rect(0, 0, 1, 1);
+    assert!(dead.undo());
+    assert_eq!(dead.population(), 0.0);
+
+    dead.draw_line(0, 0, 3, 6);
+    assert_eq!(
+        live(&dead),
+        [(0, 0), (1, 1), (1, 2), (2, 3), (2, 4), (3, 5), (3, 6)]
+    );
+    dead.erase_line(1, 0, 1, 7);
+    assert_eq!(dead.population(), 5.0);
+    dead.draw_line(7, 5, 7, 20);
+    assert_eq!(dead.population(), 8.0);
+
+    torus.clear_rect(0, 0, 6, 6);
+    torus.draw_line(0, -2, 0, 1);
+    assert_eq!(live(&torus), [(0, 0), (0, 1), (0, 4), (0, 5)]);
+
+    // Lines between the furthest cells are clipped to the board, or wrap
+    // round it a bounded number of times, without overflowing.
+    torus.draw_line(1, 0, 1, i32::MAX);
+    assert_eq!(torus.population(), 10.0);
+    torus.erase_line(i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+    torus.draw_line(i32::MAX, i32::MIN, i32::MIN, i32::MAX);
+    let mut dead = Universe::with_size(8, 8);
+    dead.set_topology(Topology::DeadEdge);
+    dead.draw_line(i32::MIN, i32::MIN, i32::MAX, i32::MAX);
+    let diagonal: Vec<_> = (0..8).map(|i| (i, i)).collect();
+    assert_eq!(live(&dead), diagonal);
+    dead.draw_line(i32::MIN, 0, i32::MAX, 0);
+    dead.draw_line(3, i32::MAX, 3, i32::MIN);
+    assert_eq!(dead.population(), 8.0 + 7.0 + 6.0);
+    // Every cell of this one has row + column = -1, off the board.
+    dead.erase_line(i32::MAX, i32::MIN, i32::MIN, i32::MAX);
+    assert_eq!(dead.population(), 21.0);
+}
@@ -1,16 +1,18 @@
-//! Test suite for the Web and headless browsers.
-
-#![cfg(target_arch = "wasm32")]
+//! Test suite for the Web and headless browsers, which also runs natively
+//! with `cargo test`.
 
 extern crate wasm_bindgen_test;
 extern crate wasm_game_of_life;
 
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen_test::*;
 use wasm_game_of_life::Universe;
 
+#[cfg(target_arch = "wasm32")]
 wasm_bindgen_test_configure!(run_in_browser);
 
-#[wasm_bindgen_test]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
 fn pass() {
     assert_eq!(1 + 1, 2);
 }
@@ -35,7 +37,8 @@ pub fn expected_spaceship() -> Universe {
 }
 
 
-#[wasm_bindgen_test]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
 pub fn test_tick() {
     // Let's create a smaller Universe with a small spaceship to test!
     let mut input_universe = input_spaceship();
@@ -6,16 +6,22 @@ const GRID_COLOR = "#CCCCCC";
 const DEAD_COLOR = "#FFFFFF";
 //const ALIVE_COLOR = "#000000";
 const ALIVE_COLOR = "#338dff";
+const DYING_COLOR = "#a8cdff";
 const pre = document.getElementById("game-of-life-canvas");
 const universe = Universe.new();
-const width = universe.width();
-const height = universe.height();
+let width = universe.width();
+let height = universe.height();
 
 // Give the canvas room for all of our cells and a 1px border
 // around each of them.
 const canvas = document.getElementById("game-of-life-canvas");
-canvas.height = (CELL_SIZE + 1) * height + 1;
-canvas.width = (CELL_SIZE + 1) * width + 1;
+const fitCanvas = () => {
+  width = universe.width();
+  height = universe.height();
+  canvas.height = (CELL_SIZE + 1) * height + 1;
+  canvas.width = (CELL_SIZE + 1) * width + 1;
+};
+fitCanvas();
 
 const ctx = canvas.getContext('2d');
 
@@ -38,52 +44,66 @@ const drawGrid = () => {
   ctx.stroke();
 };
 
-const getIndex = (row, column) => {
-  return row * width + column;
+const isAlive = (cells, stride, row, column) => {
+  return (cells[row * stride + (column >> 3)] & (1 << (column & 7))) !== 0;
+};
+
+// Paint one cell given the live bits and, under a Generations rule, the
+// ages.
+const drawCell = (cells, stride, ages, row, col) => {
+  ctx.fillStyle = isAlive(cells, stride, row, col)
+    ? ALIVE_COLOR
+    : ages !== null && ages[row * width + col] !== Cell.Dead
+      ? DYING_COLOR
+      : DEAD_COLOR;
+
+  ctx.fillRect(
+    col * (CELL_SIZE + 1) + 1,
+    row * (CELL_SIZE + 1) + 1,
+    CELL_SIZE,
+    CELL_SIZE
+  );
+};
+
+const cellViews = () => {
+  const stride = universe.stride();
+  const cells = new Uint8Array(memory.buffer, universe.cells(), stride * height);
+  // Dying states of a Generations rule, one byte per cell.
+  const ages = universe.states() > 2
+    ? new Uint8Array(memory.buffer, universe.ages(), width * height)
+    : null;
+  return [cells, stride, ages];
 };
 
 const drawCells = () => {
-  const cellsPtr = universe.cells();
-  const cells = new Uint8Array(memory.buffer, cellsPtr, width * height);
+  const [cells, stride, ages] = cellViews();
 
   ctx.beginPath();
 
   for (let row = 0; row < height; row++) {
     for (let col = 0; col < width; col++) {
-      const idx = getIndex(row, col);
-
-      ctx.fillStyle = cells[idx] === Cell.Dead
-        ? DEAD_COLOR
-        : ALIVE_COLOR;
-
-      ctx.fillRect(
-        col * (CELL_SIZE + 1) + 1,
-        row * (CELL_SIZE + 1) + 1,
-        CELL_SIZE,
-        CELL_SIZE
-      );
+      drawCell(cells, stride, ages, row, col);
     }
   }
 
   ctx.stroke();
 };
 
-const  getRowCol = () => {
-  const boundingRect = canvas.getBoundingClientRect();
-
-  const scaleX = canvas.width / boundingRect.width;
-  const scaleY = canvas.height / boundingRect.height;
+// Repaint only the cells the last tick changed.
+const drawChanges = () => {
+  const [cells, stride, ages] = cellViews();
+  const changes = new Uint32Array(memory.buffer, universe.changes(), universe.changes_len());
 
-  const canvasLeft = (event.clientX - boundingRect.left) * scaleX;
-  const canvasTop = (event.clientY - boundingRect.top) * scaleY;
+  ctx.beginPath();
 
-  const row = Math.min(Math.floor(canvasTop / (CELL_SIZE + 1)), height - 1);
-  const col = Math.min(Math.floor(canvasLeft / (CELL_SIZE + 1)), width - 1);
+  for (const idx of changes) {
+    drawCell(cells, stride, ages, Math.floor(idx / width), idx % width);
+  }
 
-  return [row, col]
-}
+  ctx.stroke();
+};
 
-canvas.addEventListener("click", event => {
+const getRowCol = (event) => {
   const boundingRect = canvas.getBoundingClientRect();
 
   const scaleX = canvas.width / boundingRect.width;
@@ -95,15 +115,80 @@ canvas.addEventListener("click", event => {
   const row = Math.min(Math.floor(canvasTop / (CELL_SIZE + 1)), height - 1);
   const col = Math.min(Math.floor(canvasLeft / (CELL_SIZE + 1)), width - 1);
 
-  universe.toggle_cell(row, col);
+  return [row, col]
+}
+
+const toolSelect = document.getElementById("tool");
+
+canvas.addEventListener("click", event => {
+  const [row, col] = getRowCol(event);
+
+  if (event.shiftKey) {
+    universe.place_pattern(
+      stampSelect.value,
+      row,
+      col,
+      Number(stampRotation.value),
+      stampFlip.checked
+    );
+  } else if (!event.ctrlKey && toolSelect.value === "toggle") {
+    universe.toggle_cell(row, col);
+  }
 
   drawGrid();
   drawCells();
 });
 
+// The line and box tools work from where the mouse goes down to where it
+// comes up.
+let dragStart = null;
+canvas.addEventListener("mousedown", event => {
+  dragStart = getRowCol(event);
+});
+canvas.addEventListener("mouseup", event => {
+  const start = dragStart;
+  dragStart = null;
+  if (start === null || event.shiftKey || event.ctrlKey) {
+    return;
+  }
+  const [startRow, startCol] = start;
+  const [row, col] = getRowCol(event);
+  const top = Math.min(row, startRow);
+  const left = Math.min(col, startCol);
+  const boxWidth = Math.abs(col - startCol) + 1;
+  const boxHeight = Math.abs(row - startRow) + 1;
+  switch (toolSelect.value) {
+    case "line":
+      universe.draw_line(startRow, startCol, row, col);
+      break;
+    case "erase-line":
+      universe.erase_line(startRow, startCol, row, col);
+      break;
+    case "fill":
+      universe.fill_rect(top, left, boxWidth, boxHeight);
+      break;
+    case "clear":
+      universe.clear_rect(top, left, boxWidth, boxHeight);
+      break;
+    default:
+      return;
+  }
+  redraw();
+});
+
+const stampSelect = document.getElementById("stamp");
+const stampRotation = document.getElementById("stamp-rotation");
+const stampFlip = document.getElementById("stamp-flip");
+for (const name of Universe.pattern_names()) {
+  const option = document.createElement("option");
+  option.value = name;
+  option.textContent = name;
+  stampSelect.appendChild(option);
+}
+
 document.addEventListener('click', function (e) {
   if (e.ctrlKey) {
-    const ret = getRowCol();
+    const ret = getRowCol(e);
     const row = ret[0];
     const col = ret[1];
     universe.launch_glider(row, col);
@@ -112,6 +197,178 @@ document.addEventListener('click', function (e) {
   }
 });
 
+const ruleInput = document.getElementById("rule");
+ruleInput.value = universe.rule();
+ruleInput.addEventListener("change", event => {
+  try {
+    universe.set_rule(ruleInput.value);
+    hashlifeInput.checked = universe.is_hashlife();
+    ruleInput.setCustomValidity("");
+  } catch (err) {
+    ruleInput.setCustomValidity(err);
+    ruleInput.reportValidity();
+  }
+  drawCells();
+});
+
+const patternFile = document.getElementById("pattern-file");
+patternFile.addEventListener("change", async event => {
+  const file = patternFile.files[0];
+  if (!file) {
+    return;
+  }
+  try {
+    universe.load_pattern(await file.text());
+    patternFile.setCustomValidity("");
+  } catch (err) {
+    patternFile.setCustomValidity(err);
+    patternFile.reportValidity();
+  }
+  ruleInput.value = universe.rule();
+  hashlifeInput.checked = universe.is_hashlife();
+  drawCells();
+});
+
+document.getElementById("export-rle").addEventListener("click", event => {
+  const blob = new Blob([universe.to_rle()], { type: "text/plain" });
+  const link = document.createElement("a");
+  link.href = URL.createObjectURL(blob);
+  link.download = "pattern.rle";
+  link.click();
+  URL.revokeObjectURL(link.href);
+});
+
+const hashlifeInput = document.getElementById("hashlife");
+hashlifeInput.addEventListener("change", event => {
+  try {
+    if (hashlifeInput.checked) {
+      universe.use_hashlife();
+    } else {
+      universe.use_naive();
+    }
+    hashlifeInput.setCustomValidity("");
+  } catch (err) {
+    hashlifeInput.checked = false;
+    hashlifeInput.setCustomValidity(err);
+    hashlifeInput.reportValidity();
+  }
+});
+
+document.getElementById("jump").addEventListener("click", event => {
+  universe.step_pow2(10);
+  drawCells();
+});
+
+// Arrow keys move the HashLife window by a quarter of the board.
+document.addEventListener("keydown", event => {
+  if (!universe.is_hashlife()) {
+    return;
+  }
+  const moves = {
+    ArrowUp: [-height / 4, 0],
+    ArrowDown: [height / 4, 0],
+    ArrowLeft: [0, -width / 4],
+    ArrowRight: [0, width / 4],
+  };
+  const move = moves[event.key];
+  if (move) {
+    universe.set_viewport(
+      universe.viewport_top() + Math.round(move[0]),
+      universe.viewport_left() + Math.round(move[1])
+    );
+    drawCells();
+    event.preventDefault();
+  }
+});
+
+const densityInput = document.getElementById("density");
+const seedInput = document.getElementById("seed");
+document.getElementById("randomize").addEventListener("click", event => {
+  universe.randomize(Number(densityInput.value), Number(seedInput.value) >>> 0);
+  redraw();
+});
+
+const boardWidthInput = document.getElementById("board-width");
+const boardHeightInput = document.getElementById("board-height");
+const anchorInput = document.getElementById("anchor");
+boardWidthInput.value = width;
+boardHeightInput.value = height;
+document.getElementById("resize").addEventListener("click", event => {
+  universe.resize(
+    Number(boardWidthInput.value),
+    Number(boardHeightInput.value),
+    Number(anchorInput.value)
+  );
+  redraw();
+});
+
+const topologyInput = document.getElementById("topology");
+topologyInput.value = universe.topology();
+topologyInput.addEventListener("change", event => {
+  universe.set_topology(Number(topologyInput.value));
+});
+
+const generationInput = document.getElementById("generation");
+const statsDiv = document.getElementById("stats");
+let reportedCycle = false;
+const showStats = () => {
+  generationInput.value = universe.generation();
+  statsDiv.textContent = `Population ${universe.population()}`;
+};
+
+const sizeChanged = () => universe.width() !== width || universe.height() !== height;
+
+const redraw = () => {
+  if (sizeChanged()) {
+    fitCanvas();
+    boardWidthInput.value = width;
+    boardHeightInput.value = height;
+  }
+  showStats();
+  drawGrid();
+  drawCells();
+};
+
+generationInput.addEventListener("change", event => {
+  pause();
+  const target = generationInput.value;
+  if (universe.go_to_generation(Number(target))) {
+    generationInput.setCustomValidity("");
+  } else {
+    generationInput.setCustomValidity(`Can't go to generation ${target}`);
+    generationInput.reportValidity();
+  }
+  redraw();
+});
+
+document.getElementById("step-back").addEventListener("click", event => {
+  pause();
+  universe.step_back();
+  redraw();
+});
+
+document.getElementById("undo").addEventListener("click", event => {
+  universe.undo();
+  redraw();
+});
+
+document.getElementById("redo").addEventListener("click", event => {
+  universe.redo();
+  redraw();
+});
+
+document.addEventListener("keydown", event => {
+  if ((event.ctrlKey || event.metaKey) && event.key.toLowerCase() === "z") {
+    if (event.shiftKey) {
+      universe.redo();
+    } else {
+      universe.undo();
+    }
+    redraw();
+    event.preventDefault();
+  }
+});
+
 const fps = new class {
   constructor() {
     this.fps = document.getElementById("fps");
@@ -186,8 +443,27 @@ const renderLoop = () => {
   fps.render();
   universe.tick();
 
-  drawGrid();
-  drawCells();
+  // A growing board may have gained rows or columns, moving every cell.
+  // Otherwise only the cells that changed need painting.
+  if (sizeChanged()) {
+    redraw();
+  } else {
+    showStats();
+    drawChanges();
+  }
+
+  // Stop once the board settles, e.g. "period-2 oscillator reached at gen 412".
+  const cycle = universe.cycle_report();
+  if (cycle !== undefined) {
+    statsDiv.textContent += `, ${cycle}`;
+    if (!reportedCycle) {
+      reportedCycle = true;
+      pause();
+      return;
+    }
+  } else {
+    reportedCycle = false;
+  }
 
   animationLoopID = requestAnimationFrame(renderLoop);
 };